@@ -9,7 +9,7 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
+	// not include the `self` or `origin: T::RuntimeOrigin` parameter, which we always assume are the
 	// first two parameters to these calls.
 	let args_name = methods
 		.iter()
@@ -23,13 +23,20 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a vector of the weight expressions of each of the functions in `fn_name`.
+	let weight = methods.iter().map(|method| &method.weight).collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
+	// and the `Dispatch` trait logic to route an `origin` to access those functions.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
+		//
+		// Calls are encoded with the derived codec: the index of the call is its position in the
+		// `impl`, so calls must not be reordered once a chain is live.
 		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
 		pub enum Call<T: Config> {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
@@ -37,24 +44,44 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
+		// appropriate function call with all arguments, including the `origin`.
 		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
+			type Origin = T::RuntimeOrigin;
 			type Call = Call<T>;
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
+			fn dispatch(
+				&mut self,
+				origin: Self::Origin,
+				call: Self::Call,
+			) -> crate::support::DispatchResultWithPostInfo {
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
+							let post_info = self.#fn_name(
+								// Note that we assume the first argument of every call is the `origin`.
+								origin,
 								#( #args_name ),*
 							)?;
+							// Calls returning `DispatchResult` report no actual weight.
+							Ok(post_info.into())
+						},
+					)*
+				}
+			}
+		}
+
+		// The weight of each call, given by the `#[weight(...)]` attribute of its function. The
+		// weight expression can use the arguments of the call, by reference.
+		impl<T: Config> crate::support::GetDispatchInfo for Call<T> {
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
+					#(
+						#[allow(unused_variables)]
+						Call::#fn_name { #( #args_name ),* } => {
+							crate::support::DispatchInfo { weight: #weight }
 						},
 					)*
 				}
-				Ok(())
 			}
 		}
 	};
@@ -4,7 +4,7 @@ use syn::spanned::Spanned;
 // Custom keywords we match to when parsing the calls in a pallet.
 mod keyword {
 	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(RuntimeOrigin);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
@@ -25,10 +25,14 @@ pub struct CallVariantDef {
 	pub name: syn::Ident,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The expression given in the `#[weight(...)]` attribute of the function.
+	pub weight: syn::Expr,
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Note that the `#[weight(...)]` attributes are removed from `item`, since they are not real
+	/// attributes and can't be left in the final code.
+	pub fn try_from(item: &mut syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing an `impl`.
 		let item_impl = if let syn::Item::Impl(item) = item {
 			item
@@ -45,7 +49,7 @@ impl CallDef {
 
 		// Here is where we will store all the callable functions.
 		let mut methods = vec![];
-		for item in item_impl.items {
+		for item in item_impl.items.iter_mut() {
 			if let syn::ImplItem::Fn(method) = item {
 				// Here is where we will store all the args for each callable functions.
 				let mut args = vec![];
@@ -59,22 +63,43 @@ impl CallDef {
 					},
 				}
 
-				// The second argument should be the `caller: T::AccountId` argument.
+				// The second argument should be the `origin: T::RuntimeOrigin` argument.
 				match method.sig.inputs.iter().skip(1).next() {
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+						// `origin: T::RuntimeOrigin`.
+						check_origin_arg(arg)?;
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, second argument should be `origin: T::RuntimeOrigin`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
 				}
 
 				let fn_name = method.sig.ident.clone();
 
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+				// Every callable function must declare its weight, which is taken out of the
+				// attributes of the function.
+				let weight_attrs = method
+					.attrs
+					.iter()
+					.filter(|attr| attr.path().is_ident("weight"))
+					.cloned()
+					.collect::<Vec<_>>();
+				method.attrs.retain(|attr| !attr.path().is_ident("weight"));
+				let weight = match weight_attrs.as_slice() {
+					[attr] => attr.parse_args::<syn::Expr>()?,
+					[] => {
+						let msg = "Invalid call, missing `#[weight(...)]` attribute";
+						return Err(syn::Error::new(method.sig.span(), msg))
+					},
+					[_, attr, ..] => {
+						let msg = "Invalid call, only one `#[weight(...)]` attribute is allowed";
+						return Err(syn::Error::new(attr.span(), msg))
+					},
+				};
+
+				// Parsing the rest of the args. Skipping 2 for `self` and `origin`.
 				for arg in method.sig.inputs.iter().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
@@ -96,7 +121,7 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef { name: fn_name, args, weight });
 			}
 		}
 
@@ -105,33 +130,33 @@ impl CallDef {
 	}
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Check origin arg is exactly: `origin: T::RuntimeOrigin`.
 ///
 /// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
 	pub struct CheckDispatchableFirstArg;
 	impl syn::parse::Parse for CheckDispatchableFirstArg {
 		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
 			input.parse::<keyword::T>()?;
 			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
+			input.parse::<keyword::RuntimeOrigin>()?;
 			Ok(Self)
 		}
 	}
 
-	// This checks the arg name is `caller` or `_caller`.
+	// This checks the arg name is `origin` or `_origin`.
 	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
+		// We also support the name as `_origin` for when the variable is unused.
+		if &ident.ident != "origin" && &ident.ident != "_origin" {
+			let msg = "Invalid name for second parameter: expected `origin: T::RuntimeOrigin`";
 			return Err(syn::Error::new(ident.span(), msg))
 		}
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+	// This checks the type is `T::RuntimeOrigin` with `CheckDispatchableFirstArg`
 	let ty = &arg.ty;
 	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
+		let msg = "Invalid type for second parameter: expected `origin: T::RuntimeOrigin`";
 		let mut err = syn::Error::new(ty.span(), msg);
 		err.combine(e);
 		err
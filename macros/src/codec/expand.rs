@@ -0,0 +1,179 @@
+use super::parse::{CodecData, CodecDef, FieldsDef, FieldsStyle};
+use quote::{format_ident, quote};
+
+/// See the `fn derive_encode` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_encode(def: CodecDef) -> proc_macro2::TokenStream {
+	let CodecDef { name, mut generics, data } = def;
+
+	// Every field must be encodable, either as is or in the compact form.
+	add_field_bounds(&mut generics, &data, quote!(crate::support::codec::Encode));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	let body = match &data {
+		// A struct is the concatenation of its fields.
+		CodecData::Struct(fields) => {
+			let encode_fields = fields.fields.iter().map(|field| {
+				let member = &field.member;
+				encode_field(&quote!(&self.#member), field.compact)
+			});
+			quote! { #( #encode_fields )* }
+		},
+		// An enum is the index of the variant, followed by the fields of the variant.
+		CodecData::Enum(variants) => {
+			let arms = variants.iter().enumerate().map(|(index, (variant, fields))| {
+				let index = index as u8;
+				let bindings = field_bindings(fields);
+				let pattern = construct(quote!(Self::#variant), fields, &bindings);
+				let encode_fields = fields
+					.fields
+					.iter()
+					.zip(&bindings)
+					.map(|(field, binding)| encode_field(&quote!(#binding), field.compact));
+				quote! {
+					#pattern => {
+						__dest.push(#index);
+						#( #encode_fields )*
+					}
+				}
+			});
+			quote! {
+				match self {
+					#( #arms, )*
+				}
+			}
+		},
+	};
+
+	quote! {
+		impl #impl_generics crate::support::codec::Encode for #name #ty_generics #where_clause {
+			fn encode_to(&self, __dest: &mut Vec<u8>) {
+				#body
+			}
+		}
+	}
+}
+
+/// See the `fn derive_decode` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_decode(def: CodecDef) -> proc_macro2::TokenStream {
+	let CodecDef { name, mut generics, data } = def;
+
+	// Every field must be decodable, either as is or from the compact form.
+	add_field_bounds(&mut generics, &data, quote!(crate::support::codec::Decode));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	let body = match &data {
+		// The fields of a struct are decoded in order.
+		CodecData::Struct(fields) => {
+			let bindings = field_bindings(fields);
+			let decode_fields = decode_fields(fields, &bindings);
+			let value = construct(quote!(Self), fields, &bindings);
+			quote! {
+				#( #decode_fields )*
+				Ok(#value)
+			}
+		},
+		// The index of the variant selects which fields are decoded.
+		CodecData::Enum(variants) => {
+			let arms = variants.iter().enumerate().map(|(index, (variant, fields))| {
+				let index = index as u8;
+				let bindings = field_bindings(fields);
+				let decode_fields = decode_fields(fields, &bindings);
+				let value = construct(quote!(Self::#variant), fields, &bindings);
+				quote! {
+					#index => {
+						#( #decode_fields )*
+						Ok(#value)
+					}
+				}
+			});
+			quote! {
+				match <u8 as crate::support::codec::Decode>::decode(__input)? {
+					#( #arms, )*
+					_ => Err(crate::support::codec::Error("invalid enum variant index")),
+				}
+			}
+		},
+	};
+
+	quote! {
+		impl #impl_generics crate::support::codec::Decode for #name #ty_generics #where_clause {
+			fn decode(__input: &mut &[u8]) -> Result<Self, crate::support::codec::Error> {
+				#body
+			}
+		}
+	}
+}
+
+/// Adds a `where` bound for the type of every field, instead of bounding the generic parameters.
+/// This way a type like `Event<T: Config>` only requires the associated types of `T` which are
+/// actually used to be encodable.
+fn add_field_bounds(generics: &mut syn::Generics, data: &CodecData, bound: proc_macro2::TokenStream) {
+	let fields = match data {
+		CodecData::Struct(fields) => vec![fields],
+		CodecData::Enum(variants) => variants.iter().map(|(_, fields)| fields).collect(),
+	};
+	let where_clause = generics.make_where_clause();
+	for field in fields.into_iter().flat_map(|fields| &fields.fields) {
+		let ty = &field.ty;
+		let predicate = if field.compact {
+			quote!(#ty: crate::support::codec::HasCompact)
+		} else {
+			quote!(#ty: #bound)
+		};
+		where_clause.predicates.push(syn::parse_quote!(#predicate));
+	}
+}
+
+/// Generates a local variable name for each field.
+fn field_bindings(fields: &FieldsDef) -> Vec<syn::Ident> {
+	fields
+		.fields
+		.iter()
+		.enumerate()
+		.map(|(i, field)| match &field.member {
+			syn::Member::Named(ident) => ident.clone(),
+			syn::Member::Unnamed(_) => format_ident!("field_{}", i),
+		})
+		.collect()
+}
+
+/// Builds `path { a, b }`, `path(field_0, field_1)` or `path` depending on the fields style. It is
+/// used both as a pattern and as an expression.
+fn construct(
+	path: proc_macro2::TokenStream,
+	fields: &FieldsDef,
+	bindings: &[syn::Ident],
+) -> proc_macro2::TokenStream {
+	match fields.style {
+		FieldsStyle::Named => quote!(#path { #( #bindings ),* }),
+		FieldsStyle::Unnamed => quote!(#path( #( #bindings ),* )),
+		FieldsStyle::Unit => quote!(#path),
+	}
+}
+
+/// Encodes the field behind the reference `value`. The generated code names its arguments
+/// `__dest` and `__input`, so they can't clash with the bindings of the fields.
+fn encode_field(value: &proc_macro2::TokenStream, compact: bool) -> proc_macro2::TokenStream {
+	if compact {
+		quote!(crate::support::codec::HasCompact::encode_compact_to(#value, __dest);)
+	} else {
+		quote!(crate::support::codec::Encode::encode_to(#value, __dest);)
+	}
+}
+
+/// Decodes every field into its binding.
+fn decode_fields(fields: &FieldsDef, bindings: &[syn::Ident]) -> Vec<proc_macro2::TokenStream> {
+	fields
+		.fields
+		.iter()
+		.zip(bindings)
+		.map(|(field, binding)| {
+			let ty = &field.ty;
+			if field.compact {
+				quote!(let #binding = <#ty as crate::support::codec::HasCompact>::decode_compact(__input)?;)
+			} else {
+				quote!(let #binding = <#ty as crate::support::codec::Decode>::decode(__input)?;)
+			}
+		})
+		.collect()
+}
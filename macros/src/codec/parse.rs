@@ -0,0 +1,122 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing a type deriving
+/// `Encode` or `Decode`.
+#[derive(Debug)]
+pub struct CodecDef {
+	/// The name of the type.
+	pub name: syn::Ident,
+	/// The generics of the type, which are reused in the generated `impl`.
+	pub generics: syn::Generics,
+	/// The shape of the type. See `CodecData`.
+	pub data: CodecData,
+}
+
+/// The shape of a type deriving `Encode` or `Decode`.
+#[derive(Debug)]
+pub enum CodecData {
+	/// A struct and its fields.
+	Struct(FieldsDef),
+	/// An enum and its variants, in declaration order. The position of a variant in this list is
+	/// its index in the encoding.
+	Enum(Vec<(syn::Ident, FieldsDef)>),
+}
+
+/// The fields of a struct or of an enum variant.
+#[derive(Debug)]
+pub struct FieldsDef {
+	/// Whether the fields are named (`{ a: u32 }`), unnamed (`(u32)`), or there are none.
+	pub style: FieldsStyle,
+	/// The fields, in declaration order. See `FieldDef`.
+	pub fields: Vec<FieldDef>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FieldsStyle {
+	Named,
+	Unnamed,
+	Unit,
+}
+
+/// This is the metadata we keep about each field.
+#[derive(Debug)]
+pub struct FieldDef {
+	/// The name of the field, or its position for unnamed fields.
+	pub member: syn::Member,
+	/// The type of the field.
+	pub ty: syn::Type,
+	/// Whether the field is marked with `#[codec(compact)]`.
+	pub compact: bool,
+}
+
+impl CodecDef {
+	pub fn try_from(input: syn::DeriveInput) -> syn::Result<Self> {
+		let data = match input.data {
+			syn::Data::Struct(data) => CodecData::Struct(FieldsDef::try_from(data.fields)?),
+			syn::Data::Enum(data) => {
+				// The variant index is encoded as a single byte.
+				if data.variants.len() > u8::MAX as usize + 1 {
+					let msg = "Invalid codec derive, too many variants";
+					return Err(syn::Error::new(input.ident.span(), msg))
+				}
+
+				let mut variants = vec![];
+				for variant in data.variants {
+					// The index of a variant is its position, explicit discriminants would be
+					// misleading.
+					if variant.discriminant.is_some() {
+						let msg = "Invalid codec derive, variants can't have explicit discriminants";
+						return Err(syn::Error::new(variant.span(), msg))
+					}
+					variants.push((variant.ident, FieldsDef::try_from(variant.fields)?));
+				}
+				CodecData::Enum(variants)
+			},
+			syn::Data::Union(_) => {
+				let msg = "Invalid codec derive, unions are not supported";
+				return Err(syn::Error::new(input.ident.span(), msg))
+			},
+		};
+
+		Ok(Self { name: input.ident, generics: input.generics, data })
+	}
+}
+
+impl FieldsDef {
+	fn try_from(fields: syn::Fields) -> syn::Result<Self> {
+		let style = match &fields {
+			syn::Fields::Named(_) => FieldsStyle::Named,
+			syn::Fields::Unnamed(_) => FieldsStyle::Unnamed,
+			syn::Fields::Unit => FieldsStyle::Unit,
+		};
+
+		let mut defs = vec![];
+		for (i, field) in fields.into_iter().enumerate() {
+			let compact = is_compact(&field.attrs)?;
+			let member = match field.ident {
+				Some(ident) => syn::Member::Named(ident),
+				None => syn::Member::Unnamed(syn::Index::from(i)),
+			};
+			defs.push(FieldDef { member, ty: field.ty, compact });
+		}
+
+		Ok(Self { style, fields: defs })
+	}
+}
+
+/// Checks if the field is marked with `#[codec(compact)]`, which is the only supported `codec`
+/// attribute.
+fn is_compact(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+	let mut compact = false;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("codec")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("compact") {
+				compact = true;
+				Ok(())
+			} else {
+				Err(meta.error("Invalid codec attribute, expected `compact`"))
+			}
+		})?;
+	}
+	Ok(compact)
+}
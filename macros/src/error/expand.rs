@@ -0,0 +1,47 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { error_enum, variants } = def;
+
+	// The error index of each variant is its position in the enum.
+	let variant_index = (0..variants.len()).map(|i| i as u8).collect::<Vec<_>>();
+	// The name of each variant, used as a human readable message.
+	let variant_name = variants.iter().map(|variant| variant.to_string()).collect::<Vec<_>>();
+
+	// This quote block gives access to the index and name of each error, and converts the error
+	// into a `DispatchError`.
+	let error_impl = quote! {
+		impl #error_enum {
+			// The index of this error inside of the pallet error enum.
+			pub fn index(&self) -> u8 {
+				match self {
+					#( Self::#variants => #variant_index, )*
+				}
+			}
+
+			// The name of this error variant.
+			pub fn name(&self) -> &'static str {
+				match self {
+					#( Self::#variants => #variant_name, )*
+				}
+			}
+		}
+
+		// The pallet index is not known by the pallet itself, it is filled in by the runtime
+		// when the error is returned from a dispatched call.
+		impl From<#error_enum> for crate::support::DispatchError {
+			fn from(error: #error_enum) -> Self {
+				crate::support::DispatchError::Module(crate::support::ModuleError {
+					index: 0,
+					error: error.index(),
+					message: Some(error.name()),
+				})
+			}
+		}
+	};
+
+	// Return the generated code.
+	error_impl
+}
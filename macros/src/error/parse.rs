@@ -0,0 +1,53 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the error enum of a
+/// pallet.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// This is the name of the error enum. We mostly assume it is `Error`.
+	pub error_enum: syn::Ident,
+	/// This is the list of the variants of the error enum, in declaration order. The position of a
+	/// variant in this list is its error index.
+	pub variants: Vec<syn::Ident>,
+}
+
+impl ErrorDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::error, expected item enum"))
+		};
+
+		// We keep errors simple: they can't be generic, so they can be converted into a
+		// `DispatchError` without knowing anything about the runtime.
+		if !item_enum.generics.params.is_empty() {
+			let msg = "Invalid pallet::error, the error enum can't be generic";
+			return Err(syn::Error::new(item_enum.generics.span(), msg))
+		}
+
+		// The error index is encoded as a single byte.
+		if item_enum.variants.len() > u8::MAX as usize + 1 {
+			let msg = "Invalid pallet::error, too many variants";
+			return Err(syn::Error::new(item_enum.span(), msg))
+		}
+
+		// Here is where we will store all the variants.
+		let mut variants = vec![];
+		for variant in item_enum.variants {
+			// Only unit variants are supported, since an error is identified by its index only.
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid pallet::error, variants can't have fields";
+				return Err(syn::Error::new(variant.span(), msg))
+			}
+			if variant.discriminant.is_some() {
+				let msg = "Invalid pallet::error, variants can't have explicit discriminants";
+				return Err(syn::Error::new(variant.span(), msg))
+			}
+			variants.push(variant.ident);
+		}
+
+		Ok(Self { error_enum: item_enum.ident, variants })
+	}
+}
@@ -1,6 +1,26 @@
 mod call;
+mod codec;
+mod error;
 mod runtime;
 
+/// Expand the callable functions of a pallet.
+///
+/// Every callable function takes `origin: T::RuntimeOrigin` as its first argument after `self`,
+/// and checks that the origin is allowed to make the call, e.g. with `system::ensure_signed` or
+/// `system::ensure_root`.
+///
+/// This generates:
+/// - `enum Call` - an enum with one variant per callable function, holding its arguments, the
+///   origin excluded.
+/// - implements the trait `support::Dispatch` for the pallet, routing each `Call` variant to the
+///   matching function, with the given origin. A function returns either `DispatchResult`, or
+///   `DispatchResultWithPostInfo` when it can report that it used less than its declared weight.
+/// - implements the trait `support::GetDispatchInfo` for `Call`, with the weight declared by the
+///   `#[weight(...)]` attribute of each function. This attribute is required, and its expression
+///   can use the arguments of the function, by reference.
+/// - derives `Debug`, `Clone`, `PartialEq`, `Eq`, `Encode` and `Decode` for `Call`. Each call is
+///   encoded as its index, which is the position of the function in the `impl` block, followed by
+///   its arguments.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +29,79 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the `Error` enum of a pallet.
+///
+/// This generates:
+/// - `fn index()` and `fn name()` on the error enum, returning the position of the variant in the
+///   enum and its name.
+/// - `impl From<Error> for support::DispatchError` - which converts the error into a
+///   `DispatchError::Module`. The pallet index is filled in by the runtime during dispatch.
+///
+/// The error enum can't be generic and only supports unit variants.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
 /// This generates function implementations on `Runtime`:
-/// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
+/// - `fn new()` - which generates a new instance of the runtime over a new empty storage.
+/// - `fn from_genesis()` - which generates a new instance of the runtime with the state described
+///   by a `GenesisConfig`, calling `fn build_genesis()` on each pallet with its own config.
+/// - `fn from_storage()` - which generates an instance of the runtime over an existing storage,
+///   by instantiating all the pallets included in the runtime with `FromStorage::from_storage`. All the
+///   pallets share the same storage, and the system pallet exposes it with `fn storage()`.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, that it is chained to the last imported block, that its extrinsics match
+///   the extrinsics root of its header, that every signed extrinsic is correctly signed and that it
+///   carries the next nonce of its caller, that every unsigned extrinsic (an inherent) succeeds,
+///   and that the resulting state matches the state root of
+///   its header. The block and each extrinsic are executed inside storage transaction layers (see
+///   `support::with_transaction`). The block is initialized by every pallet before its extrinsics,
+///   and finalized once they are applied, through the trait `support::Hooks`. The weight of the
+///   extrinsics of a block is bounded by `system::Config::MAX_BLOCK_WEIGHT`, less the weight
+///   reserved by the hooks, and the caller of each extrinsic pays for its weight through the trait
+///   `support::OnChargeTransaction`, which the runtime must implement.
+/// - `fn compute_state_root()` - which executes a block without keeping its changes, and returns
+///   the state root it leads to, so that the author of the block can fill in its header.
+/// - implements the trait `support::storage::HasStorage`, so the runtime can be used with
+///   `support::with_transaction`.
+/// - implements the trait `support::FromStorage` for the type of every pallet, with
+///   `Pallet::new(&storage)`. A pallet can then depend on another one through an associated type
+///   of its `Config`, e.g. a currency (see `support::currency`), which the runtime sets to the
+///   other pallet, and reach it over its own storage.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets. The system pallet is not included. It implements `Encode` and `Decode`, as the
+///   pallet index followed by the encoded pallet call.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet, from a
+///   `system::Config::RuntimeOrigin`. The system pallet is not included. Module errors returned by a pallet are tagged with the index of the
+///   pallet, which is its position in the `Runtime` struct (system being `0`).
+/// - implements the trait `support::GetDispatchInfo` for `RuntimeCall`, with the weight of the
+///   pallet call.
+/// - implements the trait `support::Dispatchable` for `RuntimeCall`, which dispatches the call
+///   over a runtime instantiated with `fn from_storage()`, so that pallets can dispatch calls of
+///   other pallets.
+///
+/// And the code needed for events:
+/// - `enum RuntimeEvent` - an "outer"-enum representing the accumulation of all the events of all
+///   pallets, encoded like `RuntimeCall`. Each pallet must expose an `Event<T>` enum which
+///   implements `Encode` and `Decode`, and deposits its events into the event log of the system
+///   pallet, which is kept in storage. The event log is reset at the start of every block.
+///
+/// And the code needed for the genesis state:
+/// - `struct GenesisConfig` - the accumulation of the genesis configs of all pallets, with one
+///   field per pallet, system included. Each pallet must expose a `GenesisConfig<T>` struct which
+///   implements `Default`, `serde::Serialize` and `serde::Deserialize`, and a
+///   `fn build_genesis(&mut self, config: &GenesisConfig<T>)`. Missing fields take their default
+///   value, so a chain spec only needs to list what it sets.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -32,3 +109,25 @@ pub fn runtime(
 ) -> proc_macro::TokenStream {
 	runtime::runtime(attr, item)
 }
+
+/// Derive `support::codec::Encode` for a struct or an enum.
+///
+/// A struct is encoded as the concatenation of its fields, in declaration order. An enum is
+/// encoded as the index of the variant (its position in the enum) as a single byte, followed by
+/// the fields of the variant. A field marked with `#[codec(compact)]` is encoded in the compact
+/// form, see `support::codec::HasCompact`.
+///
+/// Instead of bounding the generic parameters, the generated implementation requires the type of
+/// every field to be encodable.
+#[proc_macro_derive(Encode, attributes(codec))]
+pub fn derive_encode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	codec::derive_encode(item)
+}
+
+/// Derive `support::codec::Decode` for a struct or an enum.
+///
+/// This is the counterpart of `#[derive(Encode)]`, decoding the same layout.
+#[proc_macro_derive(Decode, attributes(codec))]
+pub fn derive_decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	codec::derive_decode(item)
+}
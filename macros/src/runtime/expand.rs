@@ -9,39 +9,190 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
 	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// This is a vector of all the pallet indices, not including system which always has index 0.
+	let pallet_indices = (1..=pallets.len()).map(|i| i as u8).collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
+			// Create a new instance of the main Runtime, over a new empty storage.
+			//
+			// The genesis block is the block with number 0 and the state root of this storage, so
+			// any change made to the storage before the first block changes the genesis hash.
 			fn new() -> Self {
+				Self::from_storage(&crate::support::storage::Storage::default())
+			}
+
+			// Create a new instance of the Runtime with the genesis state described by `config`,
+			// each pallet setting up its own part of the state.
+			fn from_genesis(config: &GenesisConfig) -> Self {
+				let mut runtime = Self::new();
+				runtime.system.build_genesis(&config.system);
+				#(
+					runtime.#pallet_names.build_genesis(&config.#pallet_names);
+				)*
+				runtime
+			}
+
+			// Create an instance of the Runtime over an existing storage, by creating each pallet
+			// over it. All the pallets share the same storage.
+			fn from_storage(storage: &crate::support::storage::Storage) -> Self {
 				Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					system: <system::Pallet::<Self>>::new(storage),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#pallet_names: <#pallet_types as crate::support::FromStorage>::from_storage(storage)
 					),*
 				}
 			}
 
 			// Execute a block of extrinsics. Increments the block number.
+			//
+			// The block is executed inside a storage transaction layer, so an invalid block is
+			// rejected as a whole and leaves no changes behind. Once the extrinsics are applied, the
+			// state root must match the `state_root` of the header.
 			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
+				let state_root = block.header.state_root;
+				crate::support::with_transaction(self, |runtime| {
+					runtime.apply_block(block)?;
+					if runtime.system.storage().state_root() != state_root {
+						return Err(crate::support::DispatchError::Other(
+							"state root does not match the state after the block",
+						))
+					}
+					Ok(())
+				})
+			}
+
+			// Compute the state root the block leads to, without changing the state. Used by the
+			// author of a block to fill in the `state_root` of its header. The `state_root` of the
+			// given header is ignored.
+			fn compute_state_root(
+				&self,
+				block: types::Block,
+			) -> Result<crate::support::crypto::Hash, crate::support::DispatchError> {
+				let storage = self.system.storage();
+				storage.start_transaction();
+				let result = Self::from_storage(storage)
+					.apply_block(block)
+					.map(|_| storage.state_root());
+				storage.rollback_transaction();
+				result
+			}
+
+			// Apply all the extrinsics of a block on top of the current state.
+			//
+			// The block must be the child of the last imported block: its `parent_hash` must be the
+			// hash of the last imported header, and its timestamp can't be earlier than the
+			// timestamp of its parent. Its extrinsics must match the `extrinsics_root` of its header,
+			// so they can't be altered once the block is built. The hash of the parent is stored in
+			// the system pallet once the block is initialized.
+			//
+			// Every signed extrinsic must carry the next nonce of its caller, must fit in the weight
+			// left in the block and its caller must be able to pay its fee, otherwise the block is
+			// invalid (see `support::OnChargeTransaction`). Its call is then dispatched with the
+			// `Signed` origin, inside its own storage transaction layer, so a call which fails
+			// halfway through leaves no changes behind in any pallet, nor any event. The nonce and
+			// the fee are handled outside of that layer, so they are kept even if the call fails.
+			// The weight the call didn't use is given back to the block, and its fee refunded to
+			// the caller. Whether the call succeeded or failed is recorded with a
+			// `system::Event`, outside of that layer as well.
+			//
+			// Unsigned extrinsics are inherents, put in the block by its author: their call is
+			// dispatched with the `None` origin, and the block is invalid if it fails.
+			//
+			// Before the extrinsics, the block is initialized by every pallet with
+			// `support::Hooks::on_initialize`, and the weight it returns is registered in the block.
+			// Once the extrinsics are applied, the block is finalized by every pallet with
+			// `support::Hooks::on_finalize`. Events deposited then are recorded with the index
+			// following the last extrinsic.
+			fn apply_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+				if block.header.parent_hash != self.system.parent_hash() {
+					return Err(crate::support::DispatchError::Other(
+						"parent hash does not match the last imported block",
+					))
+				}
+				if block.header.timestamp < self.system.timestamp() {
+					return Err(crate::support::DispatchError::Other(
+						"block timestamp is earlier than the timestamp of its parent",
+					))
+				}
+				if block.header.extrinsics_root != block.extrinsics_root() {
+					return Err(crate::support::DispatchError::Other(
+						"extrinsics root does not match the extrinsics of the block",
+					))
+				}
+				// Events of the previous block are kept until a new block starts.
+				self.system.initialize_block(&block.header);
 				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
+					return Err(crate::support::DispatchError::Other(
+						"block number does not match what is expected",
+					))
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+				let block_number = self.system.block_number();
+				#(
+					let weight = crate::support::Hooks::on_initialize(&mut self.#pallet_names, block_number);
+					self.system.register_extra_weight(weight);
+				)*
+				let genesis_hash = self.system.genesis_hash();
+				let extrinsic_count = block.extrinsics.len() as u32;
+				for (i, extrinsic) in block.extrinsics.into_iter().enumerate() {
+					let support::Extrinsic { signed, call } = extrinsic.check(&genesis_hash)?;
+					if let Some((caller, nonce)) = &signed {
+						self.system.check_nonce(caller, *nonce)?;
+					}
+					let info = crate::support::GetDispatchInfo::get_dispatch_info(&call);
+					self.system.register_weight(info.weight)?;
+					self.system.set_extrinsic_index(i as u32);
+					let actual_weight = match signed {
+						Some((caller, _)) => {
+							self.system.inc_nonce(&caller);
+							crate::support::OnChargeTransaction::withdraw_fee(self, &caller, &info)?;
+							let origin = system::Origin::Signed(caller.clone()).into();
+							let res = crate::support::with_transaction(self, |runtime| {
+								runtime.dispatch(origin, call)
+							});
+							// A failed call is charged its whole declared weight.
+							let actual_weight = match &res {
+								Ok(post_info) => post_info.calc_actual_weight(&info),
+								Err(_) => info.weight,
+							};
+							crate::support::OnChargeTransaction::refund_fee(
+								self,
+								&caller,
+								&info,
+								actual_weight,
+							);
+							self.system.note_applied_extrinsic(&res);
+							actual_weight
+						},
+						// An inherent is dispatched with the `None` origin, without nonce nor fee,
+						// and must succeed.
+						None => {
+							let origin = system::Origin::None.into();
+							let res = crate::support::with_transaction(self, |runtime| {
+								runtime.dispatch(origin, call)
+							});
+							self.system.note_applied_extrinsic(&res);
+							res.map_err(|_| crate::support::InvalidTransaction::BadInherent)?
+								.calc_actual_weight(&info)
+						},
+					};
+					self.system.refund_weight(info.weight - actual_weight);
 				}
+				self.system.set_extrinsic_index(extrinsic_count);
+				#(
+					crate::support::Hooks::on_finalize(&mut self.#pallet_names, block_number);
+				)*
 				Ok(())
 			}
 		}
+
+		impl crate::support::storage::HasStorage for #runtime_struct {
+			fn storage(&self) -> &crate::support::storage::Storage {
+				self.system.storage()
+			}
+		}
 	};
 
 	// This quote block implements the `RuntimeCall` enum and implements the `Dispatch` trait.
@@ -51,41 +202,187 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, Eq)]
 		pub enum RuntimeCall {
 			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
 		}
 
+		// Encoding of the calls: the pallet index followed by the encoded pallet call.
+		impl crate::support::codec::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							dest.push(#pallet_indices);
+							crate::support::codec::Encode::encode_to(call, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::support::codec::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::support::codec::Error> {
+				match <u8 as crate::support::codec::Decode>::decode(input)? {
+					#(
+						#pallet_indices => Ok(RuntimeCall::#pallet_names(
+							crate::support::codec::Decode::decode(input)?
+						)),
+					)*
+					_ => Err(crate::support::codec::Error("invalid pallet index")),
+				}
+			}
+		}
+
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Origin = <#runtime_struct as system::Config>::RuntimeOrigin;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call from an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that the `origin` of a signed extrinsic is built from its signer, and that
+			// pallets check with `system::ensure_signed` and the like that the origin is allowed
+			// to make the call.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Origin,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> crate::support::DispatchResultWithPostInfo {
 				// This match statement will allow us to correctly route `RuntimeCall`s
 				// to the appropriate pallet level call.
 				match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							self.#pallet_names
+								.dispatch(origin, call)
+								.map_err(|e| e.with_module_index(#pallet_indices))
 						}
 					),*
 				}
-				Ok(())
 			}
 		}
+
+		// A call can be dispatched by a pallet, over the storage of the runtime, since all the state
+		// of the runtime is kept in its storage.
+		impl crate::support::Dispatchable for RuntimeCall {
+			type Origin = <#runtime_struct as system::Config>::RuntimeOrigin;
+
+			fn dispatch(
+				self,
+				storage: &crate::support::storage::Storage,
+				origin: Self::Origin,
+			) -> crate::support::DispatchResultWithPostInfo {
+				crate::support::Dispatch::dispatch(&mut #runtime_struct::from_storage(storage), origin, self)
+			}
+		}
+
+		// The weight of a call is the weight of the pallet call it holds.
+		impl crate::support::GetDispatchInfo for RuntimeCall {
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							crate::support::GetDispatchInfo::get_dispatch_info(call)
+						}
+					),*
+				}
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum, and the conversions from the events of
+	// each pallet.
+	let event_impl = quote! {
+		// These are all the events which can be emitted by the runtime.
+		// Note that it is just an accumulation of the events emitted by each pallet, and of the
+		// events of the system pallet.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum RuntimeEvent {
+			system(system::Event),
+			#( #pallet_names(#pallet_names::Event<#runtime_struct>) ),*
+		}
+
+		// Encoding of the events: the pallet index followed by the encoded pallet event.
+		impl crate::support::codec::Encode for RuntimeEvent {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					RuntimeEvent::system(event) => {
+						dest.push(0);
+						crate::support::codec::Encode::encode_to(event, dest);
+					}
+					#(
+						RuntimeEvent::#pallet_names(event) => {
+							dest.push(#pallet_indices);
+							crate::support::codec::Encode::encode_to(event, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::support::codec::Decode for RuntimeEvent {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::support::codec::Error> {
+				match <u8 as crate::support::codec::Decode>::decode(input)? {
+					0 => Ok(RuntimeEvent::system(crate::support::codec::Decode::decode(input)?)),
+					#(
+						#pallet_indices => Ok(RuntimeEvent::#pallet_names(
+							crate::support::codec::Decode::decode(input)?
+						)),
+					)*
+					_ => Err(crate::support::codec::Error("invalid pallet index")),
+				}
+			}
+		}
+
+		impl From<system::Event> for RuntimeEvent {
+			fn from(event: system::Event) -> Self {
+				RuntimeEvent::system(event)
+			}
+		}
+
+		#(
+			impl From<#pallet_names::Event<#runtime_struct>> for RuntimeEvent {
+				fn from(event: #pallet_names::Event<#runtime_struct>) -> Self {
+					RuntimeEvent::#pallet_names(event)
+				}
+			}
+		)*
+	};
+
+	// This quote block implements the `GenesisConfig` struct, which gathers the genesis config of
+	// every pallet.
+	let genesis_impl = quote! {
+		// The initial state of the chain, read from a chain spec file.
+		// Note that it is just an accumulation of the genesis configs of each pallet, and that a
+		// pallet left out of the file starts with its default genesis config.
+		#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+		#[serde(default, deny_unknown_fields)]
+		pub struct GenesisConfig {
+			pub system: system::GenesisConfig<#runtime_struct>,
+			#( pub #pallet_names: #pallet_names::GenesisConfig<#runtime_struct>, )*
+		}
+	};
+
+	// This quote block lets every pallet be created over the storage of the runtime, so a pallet
+	// can reach another one through an associated type of its `Config`, e.g. a currency.
+	let from_storage_impl = quote! {
+		#(
+			impl crate::support::FromStorage for #pallet_types {
+				fn from_storage(storage: &crate::support::storage::Storage) -> Self {
+					Self::new(storage)
+				}
+			}
+		)*
 	};
 
 	// We combine and return all the generated code.
 	quote! {
+		#genesis_impl
 		#dispatch_impl
+		#event_impl
 		#runtime_impl
+		#from_storage_impl
 	}
 	.into()
 }
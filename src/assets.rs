@@ -0,0 +1,823 @@
+use crate::support::codec::{Decode, Encode};
+use crate::support::storage::{Storage, StorageDoubleMap, StorageMap};
+use crate::support::{ArithmeticError, DispatchResult, DispatchResultWithPostInfo, Hooks, PostDispatchInfo};
+use crate::system;
+use num::{CheckedAdd, CheckedSub, Zero};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {
+    type AssetId: Debug + Clone + PartialEq + Eq + Encode + Decode;
+    type Balance: CheckedAdd + CheckedSub + Zero + PartialOrd + Copy + Debug + Encode + Decode;
+    // the maximum length of the name and of the symbol of an asset, in bytes
+    const STRING_LIMIT: usize;
+    // the least balance of an asset an account can hold, so holding the asset is never free
+    const MIN_BALANCE: Self::Balance;
+    // the most accounts, or approvals, removed by one call while an asset is destroyed
+    const REMOVE_ITEMS_LIMIT: u32;
+}
+
+// This is Assets Pallet
+// Any account can create an asset, a token with its own balances apart from the native balances,
+// and become its owner. The admin of the asset mints and burns it, and freezes its holders
+// An asset is destroyed in stages, so no call has to remove all its accounts at once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pallet<T: Config> {
+    storage: Storage,
+    // the details of every existing asset
+    assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::Balance>>,
+    // the name, symbol and decimals of an asset, once set by its owner
+    metadata: StorageMap<T::AssetId, AssetMetadata>,
+    // the holders of every asset, only accounts with a balance or frozen are kept
+    accounts: StorageDoubleMap<T::AssetId, T::AccountId, AssetAccount<T::Balance>>,
+    // what a delegate can still transfer out of an account, keyed by `(owner, delegate)`
+    approvals: StorageDoubleMap<T::AssetId, Approval<T>, T::Balance>,
+}
+
+// An approval is keyed by the account owning the funds, then the delegate spending them
+type Approval<T> = (<T as system::Config>::AccountId, <T as system::Config>::AccountId);
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct AssetDetails<AccountId, Balance> {
+    // the account which created the asset, it can set its metadata and destroy it
+    pub owner: AccountId,
+    // the account which can mint, burn, freeze and thaw the asset
+    pub admin: AccountId,
+    // the sum of all the balances of the asset
+    pub supply: Balance,
+    // the number of accounts kept for the asset, and of approvals to spend it, which are all
+    // removed when the asset is destroyed
+    pub accounts: u32,
+    pub approvals: u32,
+    pub status: AssetStatus,
+}
+
+impl<AccountId, Balance: Zero> AssetDetails<AccountId, Balance> {
+    fn new(owner: AccountId, admin: AccountId) -> Self {
+        Self { owner, admin, supply: Balance::zero(), accounts: 0, approvals: 0, status: AssetStatus::Live }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum AssetStatus {
+    Live,
+    // the owner started to destroy the asset, it can't be used anymore and its accounts and
+    // approvals are being removed
+    Destroying,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, macros::Encode, macros::Decode)]
+pub struct AssetMetadata {
+    pub name: String,
+    pub symbol: String,
+    // the number of decimals of a balance when displayed, e.g. 2 to show 1234 as 12.34
+    pub decimals: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct AssetAccount<Balance> {
+    pub balance: Balance,
+    // a frozen account can't transfer the asset, but can still receive it
+    pub is_frozen: bool,
+}
+
+// The initial state of the assets pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::AssetId: Serialize, T::AccountId: Serialize, T::Balance: Serialize",
+    deserialize = "T::AssetId: Deserialize<'de>, T::AccountId: Deserialize<'de>, T::Balance: Deserialize<'de>"
+))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // the assets created before the first block, with their owner, which is also their admin
+    pub assets: Vec<(T::AssetId, T::AccountId)>,
+    // the name, symbol and decimals of some of these assets
+    pub metadata: Vec<(T::AssetId, String, String, u8)>,
+    // the initial balances of these assets
+    pub accounts: Vec<(T::AssetId, T::AccountId, T::Balance)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { assets: Vec::new(), metadata: Vec::new(), accounts: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // `owner` created the asset `asset_id`, administered by `admin`
+    Created { asset_id: T::AssetId, owner: T::AccountId, admin: T::AccountId },
+    // the owner of `asset_id` set its metadata
+    MetadataSet { asset_id: T::AssetId, name: String, symbol: String, decimals: u8 },
+    // `amount` of `asset_id` was minted into `owner`
+    Issued { asset_id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+    // `amount` of `asset_id` was burned from `owner`
+    Burned { asset_id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+    Transferred { asset_id: T::AssetId, from: T::AccountId, to: T::AccountId, amount: T::Balance },
+    // `owner` allowed `delegate` to transfer `amount` more of its `asset_id`
+    ApprovedTransfer { asset_id: T::AssetId, owner: T::AccountId, delegate: T::AccountId, amount: T::Balance },
+    // `delegate` transferred `amount` of `asset_id` from `owner` to `destination`
+    TransferredApproved {
+        asset_id: T::AssetId,
+        owner: T::AccountId,
+        delegate: T::AccountId,
+        destination: T::AccountId,
+        amount: T::Balance,
+    },
+    Frozen { asset_id: T::AssetId, who: T::AccountId },
+    Thawed { asset_id: T::AssetId, who: T::AccountId },
+    // the owner of `asset_id` started to destroy it
+    DestructionStarted { asset_id: T::AssetId },
+    AccountsDestroyed { asset_id: T::AssetId, accounts_destroyed: u32, accounts_remaining: u32 },
+    ApprovalsDestroyed { asset_id: T::AssetId, approvals_destroyed: u32, approvals_remaining: u32 },
+    // `asset_id` was removed, once all its balances and approvals were
+    Destroyed { asset_id: T::AssetId },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // there is no asset with this id
+    Unknown,
+    // an asset with this id already exists, or the asset being destroyed still has accounts or
+    // approvals
+    InUse,
+    // the caller is not the owner or the admin of the asset
+    NoPermission,
+    // the account doesn't hold enough of the asset
+    BalanceLow,
+    // the account is frozen and can't transfer the asset
+    Frozen,
+    // the delegate isn't allowed to transfer that much out of the account
+    Unapproved,
+    // the name or the symbol is longer than `Config::STRING_LIMIT`
+    BadMetadata,
+    // the asset is being destroyed
+    AssetNotLive,
+    // the asset must be being destroyed first
+    IncorrectStatus,
+    // the balance would be left below `Config::MIN_BALANCE`, without being emptied
+    BelowMinimum,
+    // the account holds none of the asset
+    NoAccount,
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    // Creates the asset `id`, owned by the caller and administered by `admin`
+    #[weight(10)]
+    pub fn create(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, admin: T::AccountId) -> DispatchResult {
+        let owner = system::ensure_signed(origin)?;
+        if self.assets.contains_key(&id) {
+            return Err(Error::InUse.into());
+        }
+        self.assets.insert(&id, &AssetDetails::new(owner.clone(), admin.clone()));
+        self.deposit_event(Event::Created { asset_id: id, owner, admin });
+        Ok(())
+    }
+
+    #[weight(5)]
+    pub fn set_metadata(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, name: String, symbol: String,
+                        decimals: u8) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        if self.live_asset(&id)?.owner != caller {
+            return Err(Error::NoPermission.into());
+        }
+        if name.len() > T::STRING_LIMIT || symbol.len() > T::STRING_LIMIT {
+            return Err(Error::BadMetadata.into());
+        }
+        self.metadata.insert(&id, &AssetMetadata { name: name.clone(), symbol: symbol.clone(), decimals });
+        self.deposit_event(Event::MetadataSet { asset_id: id, name, symbol, decimals });
+        Ok(())
+    }
+
+    // Creates `amount` of the asset in the account of `beneficiary`, the caller must be the admin
+    #[weight(10)]
+    pub fn mint(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, beneficiary: T::AccountId,
+                amount: T::Balance) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let mut details = self.live_asset(&id)?;
+        if details.admin != caller {
+            return Err(Error::NoPermission.into());
+        }
+        details.supply = details.supply.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        let mut account = self.account(&id, &beneficiary);
+        account.balance = account.balance.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        Self::ensure_min_balance(account.balance)?;
+        self.assets.insert(&id, &details);
+        self.set_account(&id, &beneficiary, account);
+        self.deposit_event(Event::Issued { asset_id: id, owner: beneficiary, amount });
+        Ok(())
+    }
+
+    // Destroys `amount` of the asset held by `who`, the caller must be the admin
+    #[weight(10)]
+    pub fn burn(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, who: T::AccountId,
+                amount: T::Balance) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let mut details = self.live_asset(&id)?;
+        if details.admin != caller {
+            return Err(Error::NoPermission.into());
+        }
+        let mut account = self.account(&id, &who);
+        account.balance = account.balance.checked_sub(&amount).ok_or(Error::BalanceLow)?;
+        Self::ensure_min_balance(account.balance)?;
+        details.supply = details.supply.checked_sub(&amount).ok_or(ArithmeticError::Underflow)?;
+        self.assets.insert(&id, &details);
+        self.set_account(&id, &who, account);
+        self.deposit_event(Event::Burned { asset_id: id, owner: who, amount });
+        Ok(())
+    }
+
+    #[weight(10)]
+    pub fn transfer(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, to: T::AccountId,
+                    amount: T::Balance) -> DispatchResult {
+        let from = system::ensure_signed(origin)?;
+        self.do_transfer(&id, &from, &to, amount)?;
+        self.deposit_event(Event::Transferred { asset_id: id, from, to, amount });
+        Ok(())
+    }
+
+    // Allows `delegate` to transfer `amount` more of the asset out of the account of the caller,
+    // which must hold some of the asset
+    #[weight(5)]
+    pub fn approve_transfer(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, delegate: T::AccountId,
+                            amount: T::Balance) -> DispatchResult {
+        let owner = system::ensure_signed(origin)?;
+        self.live_asset(&id)?;
+        if self.account(&id, &owner).balance.is_zero() {
+            return Err(Error::NoAccount.into());
+        }
+        let key = (owner.clone(), delegate.clone());
+        let approved = self.approvals.get(&id, &key).unwrap_or_else(T::Balance::zero)
+            .checked_add(&amount)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.set_approval(&id, &key, approved);
+        self.deposit_event(Event::ApprovedTransfer { asset_id: id, owner, delegate, amount });
+        Ok(())
+    }
+
+    // Transfers `amount` of the asset from `owner` to `destination`, out of what `owner` approved
+    // the caller to transfer
+    #[weight(15)]
+    pub fn transfer_approved(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, owner: T::AccountId,
+                             destination: T::AccountId, amount: T::Balance) -> DispatchResult {
+        let delegate = system::ensure_signed(origin)?;
+        let key = (owner.clone(), delegate.clone());
+        let remaining = self.approvals.get(&id, &key).unwrap_or_else(T::Balance::zero)
+            .checked_sub(&amount)
+            .ok_or(Error::Unapproved)?;
+        self.do_transfer(&id, &owner, &destination, amount)?;
+        self.set_approval(&id, &key, remaining);
+        self.deposit_event(Event::TransferredApproved { asset_id: id, owner, delegate, destination, amount });
+        Ok(())
+    }
+
+    // Forbids `who` to transfer the asset, the caller must be the admin
+    #[weight(5)]
+    pub fn freeze(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, who: T::AccountId) -> DispatchResult {
+        self.set_frozen(origin, &id, &who, true)?;
+        self.deposit_event(Event::Frozen { asset_id: id, who });
+        Ok(())
+    }
+
+    #[weight(5)]
+    pub fn thaw(&mut self, origin: T::RuntimeOrigin, id: T::AssetId, who: T::AccountId) -> DispatchResult {
+        self.set_frozen(origin, &id, &who, false)?;
+        self.deposit_event(Event::Thawed { asset_id: id, who });
+        Ok(())
+    }
+
+    // Starts destroying the asset, the caller must be the owner
+    // From then on the asset can't be used, its accounts and approvals are removed by
+    // `destroy_accounts` and `destroy_approvals`, a batch at a time, then `finish_destroy`
+    // removes the asset itself
+    #[weight(10)]
+    pub fn start_destroy(&mut self, origin: T::RuntimeOrigin, id: T::AssetId) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let mut details = self.live_asset(&id)?;
+        if details.owner != caller {
+            return Err(Error::NoPermission.into());
+        }
+        details.status = AssetStatus::Destroying;
+        self.assets.insert(&id, &details);
+        self.deposit_event(Event::DestructionStarted { asset_id: id });
+        Ok(())
+    }
+
+    // Removes up to `Config::REMOVE_ITEMS_LIMIT` accounts of an asset being destroyed, any account
+    // can call it
+    // Every account removed costs one more unit of weight, the call is refunded for the ones there
+    // weren't
+    #[weight(5 + T::REMOVE_ITEMS_LIMIT as u64)]
+    pub fn destroy_accounts(&mut self, origin: T::RuntimeOrigin, id: T::AssetId) -> DispatchResultWithPostInfo {
+        system::ensure_signed(origin)?;
+        let mut details = self.destroying_asset(&id)?;
+        let mut removed = 0;
+        for (who, account) in self.accounts.iter_prefix(&id).into_iter().take(T::REMOVE_ITEMS_LIMIT as usize) {
+            self.accounts.remove(&id, &who);
+            details.supply = details.supply.checked_sub(&account.balance).unwrap_or_else(T::Balance::zero);
+            removed += 1;
+        }
+        details.accounts = details.accounts.saturating_sub(removed);
+        self.assets.insert(&id, &details);
+        self.deposit_event(Event::AccountsDestroyed {
+            asset_id: id,
+            accounts_destroyed: removed,
+            accounts_remaining: details.accounts,
+        });
+        Ok(PostDispatchInfo { actual_weight: Some(5 + removed as u64) })
+    }
+
+    // Same as `destroy_accounts`, for the approvals of the asset
+    #[weight(5 + T::REMOVE_ITEMS_LIMIT as u64)]
+    pub fn destroy_approvals(&mut self, origin: T::RuntimeOrigin, id: T::AssetId) -> DispatchResultWithPostInfo {
+        system::ensure_signed(origin)?;
+        let mut details = self.destroying_asset(&id)?;
+        let mut removed = 0;
+        for (key, _) in self.approvals.iter_prefix(&id).into_iter().take(T::REMOVE_ITEMS_LIMIT as usize) {
+            self.approvals.remove(&id, &key);
+            removed += 1;
+        }
+        details.approvals = details.approvals.saturating_sub(removed);
+        self.assets.insert(&id, &details);
+        self.deposit_event(Event::ApprovalsDestroyed {
+            asset_id: id,
+            approvals_destroyed: removed,
+            approvals_remaining: details.approvals,
+        });
+        Ok(PostDispatchInfo { actual_weight: Some(5 + removed as u64) })
+    }
+
+    // Removes the asset and its metadata once all its accounts and approvals are, any account can
+    // call it
+    #[weight(10)]
+    pub fn finish_destroy(&mut self, origin: T::RuntimeOrigin, id: T::AssetId) -> DispatchResult {
+        system::ensure_signed(origin)?;
+        let details = self.destroying_asset(&id)?;
+        if details.accounts > 0 || details.approvals > 0 {
+            return Err(Error::InUse.into());
+        }
+        self.metadata.remove(&id);
+        self.assets.remove(&id);
+        self.deposit_event(Event::Destroyed { asset_id: id });
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            storage: storage.clone(),
+            assets: StorageMap::new(storage, "Assets", "Asset"),
+            metadata: StorageMap::new(storage, "Assets", "Metadata"),
+            accounts: StorageDoubleMap::new(storage, "Assets", "Account"),
+            approvals: StorageDoubleMap::new(storage, "Assets", "Approvals"),
+        }
+    }
+
+    // Sets up the genesis state, before the first block
+    // Panics if an asset is created twice, if a balance is given for an unknown asset or is below
+    // the minimum, or if metadata couldn't be set by `set_metadata`
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        for (id, owner) in &config.assets {
+            assert!(!self.assets.contains_key(id), "asset {:?} is created twice", id);
+            self.assets.insert(id, &AssetDetails::new(owner.clone(), owner.clone()));
+        }
+        for (id, name, symbol, decimals) in &config.metadata {
+            assert!(self.assets.contains_key(id), "metadata of unknown asset {:?}", id);
+            assert!(name.len() <= T::STRING_LIMIT && symbol.len() <= T::STRING_LIMIT,
+                    "metadata of asset {:?} is longer than the string limit", id);
+            self.metadata.insert(id, &AssetMetadata { name: name.clone(), symbol: symbol.clone(), decimals: *decimals });
+        }
+        for (id, who, amount) in &config.accounts {
+            let mut details = self.assets.get(id).unwrap_or_else(|| panic!("balance of unknown asset {:?}", id));
+            details.supply = details.supply.checked_add(amount).expect("the supply of an asset overflows");
+            let mut account = self.account(id, who);
+            account.balance = account.balance.checked_add(amount).expect("the balance of an account overflows");
+            assert!(Self::ensure_min_balance(account.balance).is_ok(), "balance of asset {:?} below the minimum", id);
+            self.assets.insert(id, &details);
+            self.set_account(id, who, account);
+        }
+    }
+
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    // The details of the asset `id`, or `Error::Unknown` if it doesn't exist
+    fn asset(&self, id: &T::AssetId) -> Result<AssetDetails<T::AccountId, T::Balance>, Error> {
+        self.assets.get(id).ok_or(Error::Unknown)
+    }
+
+    // The details of the asset `id`, as long as it isn't being destroyed
+    fn live_asset(&self, id: &T::AssetId) -> Result<AssetDetails<T::AccountId, T::Balance>, Error> {
+        let details = self.asset(id)?;
+        match details.status {
+            AssetStatus::Live => Ok(details),
+            AssetStatus::Destroying => Err(Error::AssetNotLive),
+        }
+    }
+
+    fn destroying_asset(&self, id: &T::AssetId) -> Result<AssetDetails<T::AccountId, T::Balance>, Error> {
+        let details = self.asset(id)?;
+        match details.status {
+            AssetStatus::Live => Err(Error::IncorrectStatus),
+            AssetStatus::Destroying => Ok(details),
+        }
+    }
+
+    // A balance is either empty or at least `Config::MIN_BALANCE`
+    fn ensure_min_balance(balance: T::Balance) -> Result<(), Error> {
+        if !balance.is_zero() && balance < T::MIN_BALANCE {
+            return Err(Error::BelowMinimum);
+        }
+        Ok(())
+    }
+
+    fn account(&self, id: &T::AssetId, who: &T::AccountId) -> AssetAccount<T::Balance> {
+        self.accounts.get(id, who).unwrap_or(AssetAccount { balance: T::Balance::zero(), is_frozen: false })
+    }
+
+    // Stores the account, or removes it once it holds nothing and isn't frozen
+    // The number of accounts of the asset follows
+    fn set_account(&mut self, id: &T::AssetId, who: &T::AccountId, account: AssetAccount<T::Balance>) {
+        let existed = self.accounts.contains_key(id, who);
+        let exists = !account.balance.is_zero() || account.is_frozen;
+        if exists {
+            self.accounts.insert(id, who, &account);
+        } else {
+            self.accounts.remove(id, who);
+        }
+        self.count(id, existed, exists, |details| &mut details.accounts);
+    }
+
+    // Stores what the delegate can still transfer, or removes the approval once it is spent
+    // The number of approvals of the asset follows
+    fn set_approval(&mut self, id: &T::AssetId, key: &Approval<T>, amount: T::Balance) {
+        let existed = self.approvals.contains_key(id, key);
+        let exists = !amount.is_zero();
+        if exists {
+            self.approvals.insert(id, key, &amount);
+        } else {
+            self.approvals.remove(id, key);
+        }
+        self.count(id, existed, exists, |details| &mut details.approvals);
+    }
+
+    // Counts an item of the asset in or out, when it was just created or removed
+    fn count(&mut self, id: &T::AssetId, existed: bool, exists: bool,
+             counter: impl FnOnce(&mut AssetDetails<T::AccountId, T::Balance>) -> &mut u32) {
+        if existed == exists {
+            return;
+        }
+        if let Some(mut details) = self.assets.get(id) {
+            let count = counter(&mut details);
+            *count = if exists { count.saturating_add(1) } else { count.saturating_sub(1) };
+            self.assets.insert(id, &details);
+        }
+    }
+
+    // Same checked arithmetic as `balances::Pallet::transfer`: nothing is written unless both
+    // balances can be updated
+    fn do_transfer(&mut self, id: &T::AssetId, from: &T::AccountId, to: &T::AccountId,
+                   amount: T::Balance) -> DispatchResult {
+        self.live_asset(id)?;
+        let mut from_account = self.account(id, from);
+        if from_account.is_frozen {
+            return Err(Error::Frozen.into());
+        }
+        from_account.balance = from_account.balance.checked_sub(&amount).ok_or(Error::BalanceLow)?;
+        if from == to {
+            return Ok(());
+        }
+        let mut to_account = self.account(id, to);
+        to_account.balance = to_account.balance.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        Self::ensure_min_balance(from_account.balance)?;
+        Self::ensure_min_balance(to_account.balance)?;
+        self.set_account(id, from, from_account);
+        self.set_account(id, to, to_account);
+        Ok(())
+    }
+
+    fn set_frozen(&mut self, origin: T::RuntimeOrigin, id: &T::AssetId, who: &T::AccountId,
+                  is_frozen: bool) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        if self.live_asset(id)?.admin != caller {
+            return Err(Error::NoPermission.into());
+        }
+        let account = AssetAccount { is_frozen, ..self.account(id, who) };
+        self.set_account(id, who, account);
+        Ok(())
+    }
+}
+
+// The state of the assets, as read by the users of the chain
+#[allow(dead_code)]
+impl<T: Config> Pallet<T> {
+    pub fn details(&self, id: &T::AssetId) -> Option<AssetDetails<T::AccountId, T::Balance>> {
+        self.assets.get(id)
+    }
+
+    pub fn metadata(&self, id: &T::AssetId) -> Option<AssetMetadata> {
+        self.metadata.get(id)
+    }
+
+    // The balance of the asset `id` held by `who`
+    pub fn balance(&self, id: &T::AssetId, who: &T::AccountId) -> T::Balance {
+        self.account(id, who).balance
+    }
+
+    // What `delegate` can still transfer out of the account of `owner`
+    pub fn approval(&self, id: &T::AssetId, owner: &T::AccountId, delegate: &T::AccountId) -> T::Balance {
+        self.approvals.get(id, &(owner.clone(), delegate.clone())).unwrap_or_else(T::Balance::zero)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AssetMetadata, Error, Event, GenesisConfig};
+    use crate::support::storage::Storage;
+    use crate::support::{ArithmeticError, DispatchError, GetDispatchInfo};
+    use crate::system::{self, Origin};
+
+    #[derive(Debug, PartialEq)]
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = Event<TestConfig>;
+        type RuntimeOrigin = system::Origin<String>;
+        const MAX_BLOCK_WEIGHT: u64 = 100;
+    }
+
+    impl super::Config for TestConfig {
+        type AssetId = u32;
+        type Balance = u128;
+        const STRING_LIMIT: usize = 8;
+        const MIN_BALANCE: u128 = 5;
+        const REMOVE_ITEMS_LIMIT: u32 = 10;
+    }
+
+    fn signed(who: &str) -> Origin<String> {
+        Origin::Signed(who.to_string())
+    }
+
+    fn events(storage: &Storage) -> Vec<Event<TestConfig>> {
+        system::Pallet::<TestConfig>::new(storage).events().into_iter().map(|record| record.event).collect()
+    }
+
+    // Asset 1 is owned by Alice and administered by Bob, Alice holds 100 of it
+    fn new_pallet(storage: &Storage) -> super::Pallet<TestConfig> {
+        let mut pallet = super::Pallet::new(storage);
+        pallet.create(signed("Alice"), 1, "Bob".to_string()).unwrap();
+        pallet.mint(signed("Bob"), 1, "Alice".to_string(), 100).unwrap();
+        pallet
+    }
+
+    #[test]
+    fn create_and_set_metadata() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        assert_eq!(pallet.create(signed("Charlie"), 1, "Charlie".to_string()), Err(Error::InUse.into()));
+        let details = pallet.details(&1).unwrap();
+        assert_eq!((details.owner.as_str(), details.admin.as_str(), details.supply), ("Alice", "Bob", 100));
+
+        // only the owner sets the metadata, within the string limit
+        assert_eq!(pallet.set_metadata(signed("Bob"), 1, "Coin".to_string(), "C".to_string(), 2), Err(Error::NoPermission.into()));
+        assert_eq!(pallet.set_metadata(signed("Alice"), 1, "Long coin name".to_string(), "C".to_string(), 2), Err(Error::BadMetadata.into()));
+        assert_eq!(pallet.set_metadata(signed("Alice"), 2, "Coin".to_string(), "C".to_string(), 2), Err(Error::Unknown.into()));
+        assert_eq!(pallet.set_metadata(signed("Alice"), 1, "Coin".to_string(), "C".to_string(), 2), Ok(()));
+        assert_eq!(pallet.metadata(&1), Some(AssetMetadata { name: "Coin".to_string(), symbol: "C".to_string(), decimals: 2 }));
+        assert_eq!(events(&storage), vec![
+            Event::Created { asset_id: 1, owner: "Alice".to_string(), admin: "Bob".to_string() },
+            Event::Issued { asset_id: 1, owner: "Alice".to_string(), amount: 100 },
+            Event::MetadataSet { asset_id: 1, name: "Coin".to_string(), symbol: "C".to_string(), decimals: 2 },
+        ]);
+    }
+
+    #[test]
+    fn mint_and_burn() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        // only the admin mints and burns, even the owner can't
+        assert_eq!(pallet.mint(signed("Alice"), 1, "Alice".to_string(), 10), Err(Error::NoPermission.into()));
+        assert_eq!(pallet.burn(signed("Alice"), 1, "Alice".to_string(), 10), Err(Error::NoPermission.into()));
+        assert_eq!(pallet.mint(signed("Bob"), 2, "Alice".to_string(), 10), Err(Error::Unknown.into()));
+
+        assert_eq!(pallet.burn(signed("Bob"), 1, "Alice".to_string(), 101), Err(Error::BalanceLow.into()));
+        assert_eq!(pallet.burn(signed("Bob"), 1, "Alice".to_string(), 40), Ok(()));
+        assert_eq!(pallet.balance(&1, &"Alice".to_string()), 60);
+        assert_eq!(pallet.details(&1).unwrap().supply, 60);
+
+        // the supply can't overflow
+        assert_eq!(pallet.mint(signed("Bob"), 1, "Charlie".to_string(), u128::MAX), Err(ArithmeticError::Overflow.into()));
+        assert_eq!(pallet.balance(&1, &"Charlie".to_string()), 0);
+    }
+
+    #[test]
+    fn transfer() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 101), Err(Error::BalanceLow.into()));
+        assert_eq!(pallet.transfer(signed("Alice"), 2, "Charlie".to_string(), 10), Err(Error::Unknown.into()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 30), Ok(()));
+        assert_eq!(pallet.balance(&1, &"Alice".to_string()), 70);
+        assert_eq!(pallet.balance(&1, &"Charlie".to_string()), 30);
+        assert_eq!(pallet.details(&1).unwrap().supply, 100);
+
+        // an emptied account is removed
+        assert_eq!(pallet.transfer(signed("Charlie"), 1, "Alice".to_string(), 30), Ok(()));
+        assert!(!pallet.accounts.contains_key(&1, &"Charlie".to_string()));
+        assert_eq!(pallet.transfer(signed("Root"), 1, "Alice".to_string(), 0), Ok(()));
+        assert_eq!(pallet.transfer(Origin::Root, 1, "Alice".to_string(), 1), Err(DispatchError::BadOrigin));
+    }
+
+    #[test]
+    fn approved_transfers() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Charlie".to_string(), 10), Err(Error::Unapproved.into()));
+        assert_eq!(pallet.approve_transfer(signed("Alice"), 1, "Bob".to_string(), 20), Ok(()));
+        assert_eq!(pallet.approve_transfer(signed("Alice"), 1, "Bob".to_string(), 10), Ok(()));
+        assert_eq!(pallet.approval(&1, &"Alice".to_string(), &"Bob".to_string()), 30);
+
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Charlie".to_string(), 31), Err(Error::Unapproved.into()));
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Charlie".to_string(), 20), Ok(()));
+        assert_eq!(pallet.approval(&1, &"Alice".to_string(), &"Bob".to_string()), 10);
+        assert_eq!(pallet.balance(&1, &"Charlie".to_string()), 20);
+
+        // the approval is only used up by a successful transfer
+        pallet.burn(signed("Bob"), 1, "Alice".to_string(), 75).unwrap();
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Charlie".to_string(), 10), Err(Error::BalanceLow.into()));
+        assert_eq!(pallet.approval(&1, &"Alice".to_string(), &"Bob".to_string()), 10);
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Bob".to_string(), 5), Ok(()));
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Bob".to_string(), 5), Err(Error::BalanceLow.into()));
+        assert_eq!(events(&storage).last(), Some(&Event::TransferredApproved {
+            asset_id: 1,
+            owner: "Alice".to_string(),
+            delegate: "Bob".to_string(),
+            destination: "Bob".to_string(),
+            amount: 5,
+        }));
+    }
+
+    #[test]
+    fn frozen_accounts_can_only_receive() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        assert_eq!(pallet.freeze(signed("Alice"), 1, "Alice".to_string()), Err(Error::NoPermission.into()));
+        assert_eq!(pallet.freeze(signed("Bob"), 1, "Alice".to_string()), Ok(()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 10), Err(Error::Frozen.into()));
+        pallet.approve_transfer(signed("Alice"), 1, "Bob".to_string(), 10).unwrap();
+        assert_eq!(pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Charlie".to_string(), 10), Err(Error::Frozen.into()));
+
+        // a frozen account is kept even when empty
+        assert_eq!(pallet.freeze(signed("Bob"), 1, "Charlie".to_string()), Ok(()));
+        assert!(pallet.accounts.contains_key(&1, &"Charlie".to_string()));
+        pallet.mint(signed("Bob"), 1, "Charlie".to_string(), 10).unwrap();
+        assert_eq!(pallet.transfer(signed("Charlie"), 1, "Alice".to_string(), 10), Err(Error::Frozen.into()));
+
+        assert_eq!(pallet.thaw(signed("Bob"), 1, "Alice".to_string()), Ok(()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 10), Ok(()));
+        assert_eq!(events(&storage).last(), Some(&Event::Transferred {
+            asset_id: 1,
+            from: "Alice".to_string(),
+            to: "Charlie".to_string(),
+            amount: 10,
+        }));
+    }
+
+    #[test]
+    fn holding_the_asset_is_not_free() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        // a balance can't be left below the minimum, unless it is emptied
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 4), Err(Error::BelowMinimum.into()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 96), Err(Error::BelowMinimum.into()));
+        assert_eq!(pallet.mint(signed("Bob"), 1, "Charlie".to_string(), 4), Err(Error::BelowMinimum.into()));
+        assert_eq!(pallet.burn(signed("Bob"), 1, "Alice".to_string(), 97), Err(Error::BelowMinimum.into()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 5), Ok(()));
+        assert_eq!(pallet.transfer(signed("Charlie"), 1, "Alice".to_string(), 5), Ok(()));
+
+        // only a holder of the asset can approve a delegate
+        assert_eq!(pallet.approve_transfer(signed("Charlie"), 1, "Bob".to_string(), 10), Err(Error::NoAccount.into()));
+        assert_eq!(pallet.details(&1).unwrap().approvals, 0);
+    }
+
+    #[test]
+    fn destroy() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        pallet.set_metadata(signed("Alice"), 1, "Coin".to_string(), "C".to_string(), 2).unwrap();
+        pallet.approve_transfer(signed("Alice"), 1, "Bob".to_string(), 10).unwrap();
+        pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 10).unwrap();
+        pallet.freeze(signed("Bob"), 1, "Dave".to_string()).unwrap();
+        // Alice, Charlie and the frozen Dave are kept, the approval is spent by transferring it all
+        let details = pallet.details(&1).unwrap();
+        assert_eq!((details.accounts, details.approvals), (3, 1));
+        pallet.transfer_approved(signed("Bob"), 1, "Alice".to_string(), "Bob".to_string(), 10).unwrap();
+        pallet.transfer(signed("Bob"), 1, "Alice".to_string(), 10).unwrap();
+        let details = pallet.details(&1).unwrap();
+        assert_eq!((details.accounts, details.approvals), (3, 0));
+        pallet.approve_transfer(signed("Charlie"), 1, "Bob".to_string(), 5).unwrap();
+
+        // only the owner starts destroying the asset, which can't be used from then on
+        assert_eq!(pallet.destroy_accounts(signed("Alice"), 1), Err(Error::IncorrectStatus.into()));
+        assert_eq!(pallet.start_destroy(signed("Bob"), 1), Err(Error::NoPermission.into()));
+        assert_eq!(pallet.start_destroy(signed("Alice"), 1), Ok(()));
+        assert_eq!(pallet.start_destroy(signed("Alice"), 1), Err(Error::AssetNotLive.into()));
+        assert_eq!(pallet.transfer(signed("Alice"), 1, "Charlie".to_string(), 10), Err(Error::AssetNotLive.into()));
+        assert_eq!(pallet.mint(signed("Bob"), 1, "Alice".to_string(), 10), Err(Error::AssetNotLive.into()));
+        assert_eq!(pallet.approve_transfer(signed("Alice"), 1, "Bob".to_string(), 10), Err(Error::AssetNotLive.into()));
+
+        // the asset is only removed once its accounts and approvals are, which anyone can do
+        assert_eq!(pallet.finish_destroy(signed("Charlie"), 1), Err(Error::InUse.into()));
+        let call = super::Call::<TestConfig>::destroy_accounts { id: 1 };
+        assert_eq!(call.get_dispatch_info().weight, 15);
+        // each call is refunded for the items there weren't
+        assert_eq!(pallet.destroy_accounts(signed("Charlie"), 1).unwrap().actual_weight, Some(8));
+        assert_eq!(pallet.destroy_approvals(signed("Charlie"), 1).unwrap().actual_weight, Some(6));
+        assert_eq!(pallet.finish_destroy(signed("Charlie"), 1), Ok(()));
+        assert_eq!(pallet.details(&1), None);
+        assert_eq!(pallet.metadata(&1), None);
+        assert_eq!(pallet.balance(&1, &"Alice".to_string()), 0);
+        assert_eq!(pallet.approval(&1, &"Charlie".to_string(), &"Bob".to_string()), 0);
+        assert_eq!(pallet.finish_destroy(signed("Charlie"), 1), Err(Error::Unknown.into()));
+        let events = events(&storage);
+        assert_eq!(events[events.len() - 4..], [
+            Event::DestructionStarted { asset_id: 1 },
+            Event::AccountsDestroyed { asset_id: 1, accounts_destroyed: 3, accounts_remaining: 0 },
+            Event::ApprovalsDestroyed { asset_id: 1, approvals_destroyed: 1, approvals_remaining: 0 },
+            Event::Destroyed { asset_id: 1 },
+        ]);
+
+        // the id can be used again
+        assert_eq!(pallet.create(signed("Charlie"), 1, "Charlie".to_string()), Ok(()));
+        assert_eq!(pallet.details(&1).unwrap().supply, 0);
+    }
+
+    #[test]
+    fn destroy_in_batches() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        // 90 holders, each approving Alice, far more than the block weight allows to remove at once
+        pallet.mint(signed("Bob"), 1, "Alice".to_string(), 400).unwrap();
+        for i in 0..90 {
+            let holder = format!("Holder {}", i);
+            pallet.transfer(signed("Alice"), 1, holder.clone(), 5).unwrap();
+            pallet.approve_transfer(signed(&holder), 1, "Alice".to_string(), 5).unwrap();
+        }
+        let details = pallet.details(&1).unwrap();
+        assert_eq!((details.accounts, details.approvals), (91, 90));
+        pallet.start_destroy(signed("Alice"), 1).unwrap();
+
+        // every call removes at most `REMOVE_ITEMS_LIMIT` items, within its declared weight
+        let weight = super::Call::<TestConfig>::destroy_accounts { id: 1 }.get_dispatch_info().weight;
+        let mut calls = 0;
+        while pallet.details(&1).unwrap().accounts > 0 {
+            assert!(pallet.destroy_accounts(signed("Charlie"), 1).unwrap().actual_weight <= Some(weight));
+            calls += 1;
+        }
+        while pallet.details(&1).unwrap().approvals > 0 {
+            assert!(pallet.destroy_approvals(signed("Charlie"), 1).unwrap().actual_weight <= Some(weight));
+            calls += 1;
+        }
+        assert_eq!(calls, 10 + 9);
+        assert_eq!(pallet.details(&1).unwrap().supply, 0);
+        assert_eq!(pallet.finish_destroy(signed("Alice"), 1), Ok(()));
+        assert!(pallet.accounts.iter_prefix(&1).is_empty());
+        assert!(pallet.approvals.iter_prefix(&1).is_empty());
+    }
+
+    #[test]
+    fn genesis() {
+        let storage = Storage::default();
+        let mut pallet = super::Pallet::<TestConfig>::new(&storage);
+        pallet.build_genesis(&GenesisConfig {
+            assets: vec![(1, "Alice".to_string())],
+            metadata: vec![(1, "Coin".to_string(), "C".to_string(), 2)],
+            accounts: vec![(1, "Alice".to_string(), 60), (1, "Bob".to_string(), 40)],
+        });
+        let details = pallet.details(&1).unwrap();
+        assert_eq!((details.admin.as_str(), details.supply, details.accounts), ("Alice", 100, 2));
+        assert_eq!(pallet.balance(&1, &"Bob".to_string()), 40);
+        assert_eq!(pallet.metadata(&1).unwrap().decimals, 2);
+        assert_eq!(events(&storage), vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "metadata of asset 1 is longer than the string limit")]
+    fn genesis_metadata_is_bounded() {
+        super::Pallet::<TestConfig>::new(&Storage::default()).build_genesis(&GenesisConfig {
+            assets: vec![(1, "Alice".to_string())],
+            metadata: vec![(1, "Long coin name".to_string(), "C".to_string(), 2)],
+            accounts: vec![],
+        });
+    }
+}
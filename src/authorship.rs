@@ -0,0 +1,127 @@
+use crate::support::storage::{Storage, StorageValue};
+use crate::support::{DispatchResult, Hooks};
+use crate::system;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {}
+
+// This is Authorship Pallet
+// Keeps track of the author of the current block, which the author sets with an inherent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pallet<T: Config> {
+    storage: Storage,
+    // the author of the block with the given number, only the last one is kept
+    author: StorageValue<(T::BlockNumber, T::AccountId)>,
+}
+
+// The authorship pallet has nothing to set up at genesis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "", deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    #[serde(skip)]
+    _config: PhantomData<T>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { _config: PhantomData }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // `author` is the author of the current block
+    AuthorSet { author: T::AccountId },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the author of the current block is already set
+    AuthorAlreadySet,
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    // An inherent: only the author of the block can include it, as an unsigned extrinsic
+    #[weight(2)]
+    pub fn set_author(&mut self, origin: T::RuntimeOrigin, author: T::AccountId) -> DispatchResult {
+        system::ensure_none(origin)?;
+        if self.author().is_some() {
+            return Err(Error::AuthorAlreadySet.into());
+        }
+        self.author.put(&(self.block_number(), author.clone()));
+        self.deposit_event(Event::AuthorSet { author });
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            storage: storage.clone(),
+            author: StorageValue::new(storage, "Authorship", "Author"),
+        }
+    }
+
+    pub fn build_genesis(&mut self, _config: &GenesisConfig<T>) {}
+
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    fn block_number(&self) -> T::BlockNumber {
+        system::Pallet::<T>::new(&self.storage).block_number()
+    }
+
+    // The author of the current block, if it set itself
+    pub fn author(&self) -> Option<T::AccountId> {
+        self.author.get().filter(|(block_number, _)| *block_number == self.block_number()).map(|(_, author)| author)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::storage::Storage;
+    use crate::support::DispatchError;
+    use crate::system::{self, Origin};
+
+    #[derive(Debug, PartialEq)]
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = super::Event<TestConfig>;
+        type RuntimeOrigin = system::Origin<String>;
+        const MAX_BLOCK_WEIGHT: u64 = 100;
+    }
+
+    impl super::Config for TestConfig {}
+
+    #[test]
+    fn author_is_set_once_per_block() {
+        let storage = Storage::default();
+        let mut pallet = super::Pallet::<TestConfig>::new(&storage);
+        let alice = "Alice".to_string();
+        assert_eq!(pallet.author(), None);
+        // only an inherent can set the author
+        assert_eq!(pallet.set_author(Origin::Signed(alice.clone()), alice.clone()), Err(DispatchError::BadOrigin));
+        assert_eq!(pallet.set_author(Origin::Root, alice.clone()), Err(DispatchError::BadOrigin));
+
+        assert_eq!(pallet.set_author(Origin::None, alice.clone()), Ok(()));
+        assert_eq!(pallet.author(), Some(alice.clone()));
+        assert_eq!(pallet.set_author(Origin::None, "Bob".to_string()), Err(super::Error::AuthorAlreadySet.into()));
+
+        // the next block has no author until it sets one
+        system::Pallet::<TestConfig>::new(&storage).inc_block_number();
+        assert_eq!(pallet.author(), None);
+        assert_eq!(pallet.set_author(Origin::None, "Bob".to_string()), Ok(()));
+        assert_eq!(pallet.author(), Some("Bob".to_string()));
+    }
+}
@@ -1,67 +1,599 @@
-use num::{CheckedAdd, CheckedSub, Zero};
-use std::{collections::BTreeMap, fmt::Debug};
+use crate::support::codec::{Decode, Encode};
+use crate::support::currency::{
+    BalanceStatus, Currency, ExistenceRequirement, Imbalance, LockIdentifier, LockableCurrency, ReservableCurrency,
+};
+use crate::support::storage::{Storage, StorageMap, StorageValue};
+use crate::support::{
+    ArithmeticError, DispatchError, DispatchResult, DispatchResultWithPostInfo, Hooks, InvalidTransaction,
+    PostDispatchInfo,
+};
+use crate::system;
+use num::{CheckedAdd, CheckedSub, Saturating, Zero};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 
-pub trait Config: crate::system::Config {
-    type Balance: CheckedAdd + CheckedSub + Zero + Copy;
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {
+    type Balance: CheckedAdd + CheckedSub + Saturating + Zero + PartialOrd + Copy + Debug + Encode + Decode;
+    // The minimum balance of an account. An account whose total balance, free and reserved, drops
+    // below it is reaped: it is removed along with its nonce and locks, and what is left is lost
+    const EXISTENTIAL_DEPOSIT: Self::Balance;
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pallet<T: Config> {
+    storage: Storage,
     // the ket is the wallet and the value is the balance
-    balances: BTreeMap<T::AccountId, T::Balance>,
+    // only existing accounts are kept, whose total balance is at least the existential deposit
+    accounts: StorageMap<T::AccountId, AccountData<T::Balance>>,
+    // the locks of every account, the largest one is the frozen balance of the account
+    locks: StorageMap<T::AccountId, Vec<BalanceLock<T::Balance>>>,
+    // the sum of all the balances, changed by every balance which is set
+    total_issuance: StorageValue<T::Balance>,
 }
 
+// The balance of an account, split by what it can be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct AccountData<Balance> {
+    // what the account can spend, apart from what is frozen
+    pub free: Balance,
+    // what is set aside, e.g. as a deposit, and can't be spent until it is unreserved
+    pub reserved: Balance,
+    // the part of `free` which can't be transferred nor reserved, the largest lock of the account
+    pub frozen: Balance,
+}
+
+impl<Balance: Saturating + Copy> AccountData<Balance> {
+    pub fn total(&self) -> Balance {
+        self.free.saturating_add(self.reserved)
+    }
+
+    // What can be transferred or reserved
+    pub fn usable(&self) -> Balance {
+        self.free.saturating_sub(self.frozen)
+    }
+}
+
+// Funds of an account which stay in its free balance, but can't be transferred nor reserved
+// Locks overlap: the account can use what is above its largest lock. Fees can still be paid with
+// locked funds, so an account with all its funds locked can still send extrinsics
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct BalanceLock<Balance> {
+    pub id: LockIdentifier,
+    pub amount: Balance,
+}
+
+// The initial state of the balances pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::AccountId: Serialize, T::Balance: Serialize",
+    deserialize = "T::AccountId: Deserialize<'de>, T::Balance: Deserialize<'de>"
+))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // the initial balance of every endowed account
+    pub balances: Vec<(T::AccountId, T::Balance)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { balances: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // `amount` was transferred from `from` to `to`
+    Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+    // `who` paid `actual_fee` for an extrinsic, once the fee for its unused weight was refunded
+    TransactionFeePaid { who: T::AccountId, actual_fee: T::Balance },
+    // the balance of `who` was set to `free` by Root
+    BalanceSet { who: T::AccountId, free: T::Balance },
+    // `account` was reaped, losing the `amount` left below the existential deposit
+    DustLost { account: T::AccountId, amount: T::Balance },
+    // `amount` was created by Root into the balance of `who`
+    Minted { who: T::AccountId, amount: T::Balance },
+    // `amount` was destroyed by Root from the balance of `who`
+    Burned { who: T::AccountId, amount: T::Balance },
+    // `amount` of the free balance of `who` was reserved
+    Reserved { who: T::AccountId, amount: T::Balance },
+    // `amount` of the reserved balance of `who` was made free again
+    Unreserved { who: T::AccountId, amount: T::Balance },
+    // `amount` of the reserved balance of `from` was moved to the `status` balance of `to`
+    ReserveRepatriated { from: T::AccountId, to: T::AccountId, amount: T::Balance, status: BalanceStatus },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the account does not have enough balance for the operation
+    InsufficientBalance,
+    // the transfer would create an account with less than the existential deposit
+    ExistentialDeposit,
+    // the transfer would reap the sender, who asked to be kept alive
+    KeepAlive,
+    // the funds are locked, they can't be transferred nor reserved
+    LiquidityRestrictions,
+}
+
+fn min<Balance: PartialOrd>(a: Balance, b: Balance) -> Balance {
+    if a < b { a } else { b }
+}
+
+fn max<Balance: PartialOrd>(a: Balance, b: Balance) -> Balance {
+    if a < b { b } else { a }
+}
+
+// Funds taken out of an account and not put into another one yet, e.g. a fee or a slash
+// They are already gone from the total issuance: dropping the imbalance burns them, while
+// `Currency::resolve_creating` routes them into an account
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct NegativeImbalance<T: Config>(T::Balance);
+
+impl<T: Config> Imbalance<T::Balance> for NegativeImbalance<T> {
+    fn peek(&self) -> T::Balance {
+        self.0
+    }
+
+    fn split(self, amount: T::Balance) -> (Self, Self) {
+        let first = min(amount, self.0);
+        (Self(first), Self(self.0.saturating_sub(first)))
+    }
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
 #[macros::call]
 impl<T: Config> Pallet<T> {
-    pub fn transfer(&mut self, caller: T::AccountId,
-                    to: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
-        let caller_balance = self.balance(&caller);
-        let to_balance = self.balance(&to);
-        let new_caller_balance = caller_balance.checked_sub(&amount)
-            .ok_or("Insufficient balance")?;
-        let new_to_balance = to_balance.checked_add(&amount)
-            .ok_or("Overflow")?;
-        self.set_balance(&caller, new_caller_balance);
-        self.set_balance(&to, new_to_balance);
+    // A transfer reads and writes two balances. A transfer to oneself changes nothing, so it only
+    // uses the weight of reading the balance
+    // The caller is reaped if what is left of its balance is below the existential deposit
+    #[weight(10)]
+    pub fn transfer(&mut self, origin: T::RuntimeOrigin,
+                    to: T::AccountId, amount: T::Balance) -> DispatchResultWithPostInfo {
+        let caller = system::ensure_signed(origin)?;
+        self.do_transfer(caller, to, amount, ExistenceRequirement::AllowDeath)
+    }
+
+    // Same as `transfer`, but fails rather than reaping the caller
+    #[weight(10)]
+    pub fn transfer_keep_alive(&mut self, origin: T::RuntimeOrigin,
+                               to: T::AccountId, amount: T::Balance) -> DispatchResultWithPostInfo {
+        let caller = system::ensure_signed(origin)?;
+        self.do_transfer(caller, to, amount, ExistenceRequirement::KeepAlive)
+    }
 
+    // Sets the balance of any account, only allowed to Root
+    #[weight(5)]
+    pub fn force_set_balance(&mut self, origin: T::RuntimeOrigin,
+                             who: T::AccountId, new_balance: T::Balance) -> DispatchResult {
+        system::ensure_root(origin)?;
+        self.total_issuance().saturating_sub(self.balance(&who)).checked_add(&new_balance)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.set_balance(&who, new_balance);
+        // a balance below the existential deposit reaps the account
+        let free = self.balance(&who);
+        self.deposit_event(Event::BalanceSet { who, free });
+        Ok(())
+    }
+
+    // Creates `amount` into the free balance of `who`, only allowed to Root
+    #[weight(5)]
+    pub fn mint(&mut self, origin: T::RuntimeOrigin, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        system::ensure_root(origin)?;
+        self.total_issuance().checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        let new_balance = self.balance(&who).checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        if Self::is_dust(self.account(&who).total().saturating_add(amount)) {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        self.set_balance(&who, new_balance);
+        self.deposit_event(Event::Minted { who, amount });
+        Ok(())
+    }
+
+    // Destroys `amount` from the free balance of `who`, only allowed to Root, even if it is locked
+    // The account is reaped if what is left is below the existential deposit
+    #[weight(5)]
+    pub fn burn(&mut self, origin: T::RuntimeOrigin, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        system::ensure_root(origin)?;
+        let new_balance = self.balance(&who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+        let ((), dust) = self.mutate_account(&who, |account| account.free = new_balance);
+        self.deposit_event(Event::Burned { who: who.clone(), amount });
+        if !dust.is_zero() {
+            self.deposit_event(Event::DustLost { account: who, amount: dust });
+        }
         Ok(())
     }
 }
 
 impl<T: Config> Pallet<T> {
-    pub fn new() -> Self {
+    pub fn new(storage: &Storage) -> Self {
         Self {
-            balances: BTreeMap::new()
+            storage: storage.clone(),
+            accounts: StorageMap::new(storage, "Balances", "Account"),
+            locks: StorageMap::new(storage, "Balances", "Locks"),
+            total_issuance: StorageValue::new(storage, "Balances", "TotalIssuance"),
+        }
+    }
+
+    // Sets up the genesis state, before the first block
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        for (who, amount) in &config.balances {
+            self.set_balance(who, *amount);
         }
     }
 
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    // Sets the free balance of `who`, reaping the account when its total balance is below the
+    // existential deposit
+    // The total issuance follows: tokens are created or destroyed, as much as the balance changed
     pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+        self.mutate_account(who, |account| account.free = amount);
+    }
+
+    pub fn total_issuance(&self) -> T::Balance {
+        self.total_issuance.get().unwrap_or_else(T::Balance::zero)
     }
 
+    // Whether an account with this total balance can't exist
+    fn is_dust(amount: T::Balance) -> bool {
+        amount.is_zero() || amount < T::EXISTENTIAL_DEPOSIT
+    }
+
+    // Changes the account of `who`, the total issuance following its total balance
+    // The account is reaped if its total balance ends up below the existential deposit: what is left
+    // of it, the dust, is lost and returned along with the result of `f`
+    fn mutate_account<R>(&mut self, who: &T::AccountId,
+                         f: impl FnOnce(&mut AccountData<T::Balance>) -> R) -> (R, T::Balance) {
+        let mut account = self.account(who);
+        let before = account.total();
+        let result = f(&mut account);
+        let (after, dust) = match account.total() {
+            total if Self::is_dust(total) => (T::Balance::zero(), total),
+            total => (total, T::Balance::zero()),
+        };
+        let total_issuance = self.total_issuance().saturating_sub(before).saturating_add(after);
+        self.total_issuance.put(&total_issuance);
+        if after.is_zero() {
+            self.reap_account(who);
+        } else {
+            self.accounts.insert(who, &account);
+        }
+        (result, dust)
+    }
+
+    // Removes an account, which starts again from a zero nonce if it is endowed later
+    fn reap_account(&mut self, who: &T::AccountId) {
+        if self.accounts.contains_key(who) {
+            self.accounts.remove(who);
+            self.locks.remove(who);
+            system::Pallet::<T>::new(&self.storage).kill_account(who);
+        }
+    }
+
+    pub fn account_exists(&self, who: &T::AccountId) -> bool {
+        self.accounts.contains_key(who)
+    }
+
+    pub fn account(&self, who: &T::AccountId) -> AccountData<T::Balance> {
+        self.accounts.get(who).unwrap_or_else(|| {
+            AccountData { free: T::Balance::zero(), reserved: T::Balance::zero(), frozen: T::Balance::zero() }
+        })
+    }
+
+    // Moves `amount` from `from` to `to`, which must end up with at least the existential deposit
+    // `from` is reaped if it is left with less, unless it asked to be kept alive
+    fn do_transfer(&mut self, from: T::AccountId, to: T::AccountId, amount: T::Balance,
+                   existence: ExistenceRequirement) -> DispatchResultWithPostInfo {
+        let from_account = self.account(&from);
+        let new_from_balance = from_account.free.checked_sub(&amount)
+            .ok_or(Error::InsufficientBalance)?;
+        if from == to {
+            return Ok(PostDispatchInfo { actual_weight: Some(2) });
+        }
+        if amount > from_account.usable() {
+            return Err(Error::LiquidityRestrictions.into());
+        }
+        let to_account = self.account(&to);
+        let new_to_balance = to_account.free.checked_add(&amount)
+            .ok_or(ArithmeticError::Overflow)?;
+        if Self::is_dust(to_account.total().saturating_add(amount)) {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        let keep_alive = existence == ExistenceRequirement::KeepAlive;
+        if keep_alive && Self::is_dust(new_from_balance.saturating_add(from_account.reserved)) {
+            return Err(Error::KeepAlive.into());
+        }
+        let ((), dust) = self.mutate_account(&from, |account| account.free = new_from_balance);
+        self.mutate_account(&to, |account| account.free = new_to_balance);
+        self.deposit_event(Event::Transfer { from: from.clone(), to, amount });
+        if !dust.is_zero() {
+            self.deposit_event(Event::DustLost { account: from, amount: dust });
+        }
+
+        Ok(().into())
+    }
+
+    // The free balance of `who`
     pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-        *self.balances.get(who).unwrap_or(&T::Balance::zero())
+        self.account(who).free
+    }
+
+    pub fn locks(&self, who: &T::AccountId) -> Vec<BalanceLock<T::Balance>> {
+        self.locks.get(who).unwrap_or_default()
+    }
+
+    // Stores the locks of `who`, freezing as much as the largest of them
+    fn update_locks(&mut self, who: &T::AccountId, locks: Vec<BalanceLock<T::Balance>>) {
+        if !self.account_exists(who) {
+            return;
+        }
+        let frozen = locks.iter().map(|lock| lock.amount).fold(T::Balance::zero(), max);
+        self.mutate_account(who, |account| account.frozen = frozen);
+        if locks.is_empty() {
+            self.locks.remove(who);
+        } else {
+            self.locks.insert(who, &locks);
+        }
+    }
+
+    // Withdraws the fee of an extrinsic from its caller, before its call is dispatched
+    // The fee leaves the chain: it is not given to anyone
+    // Paying a fee never reaps the caller, so its nonce is kept and the extrinsic can't be replayed
+    // Locks don't apply, locked funds can pay for the extrinsics of their account
+    pub fn withdraw_fee(&mut self, who: &T::AccountId, fee: T::Balance) -> Result<(), InvalidTransaction> {
+        // the fee is burned until the call is dispatched, then what is refunded is created again
+        let _ = self.do_withdraw(who, fee, ExistenceRequirement::KeepAlive).map_err(|_| InvalidTransaction::Payment)?;
+        Ok(())
+    }
+
+    // Takes `amount` out of the free balance of `who`, whether it is locked or not
+    fn do_withdraw(&mut self, who: &T::AccountId, amount: T::Balance,
+                   existence: ExistenceRequirement) -> Result<NegativeImbalance<T>, DispatchError> {
+        let account = self.account(who);
+        let new_balance = account.free.checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+        let keep_alive = existence == ExistenceRequirement::KeepAlive;
+        if keep_alive && Self::is_dust(new_balance.saturating_add(account.reserved)) {
+            return Err(Error::KeepAlive.into());
+        }
+        self.set_balance(who, new_balance);
+        Ok(NegativeImbalance(amount))
+    }
+
+    // Gives back `refund` out of the `fee` withdrawn from `who`, once the call is dispatched
+    // The refund is lost if the call reaped `who`
+    // Returns the fee actually paid, which is burned unless the runtime routes it somewhere
+    pub fn refund_fee(&mut self, who: &T::AccountId, fee: T::Balance, refund: T::Balance) -> NegativeImbalance<T> {
+        if self.account_exists(who) {
+            self.resolve_creating(who, NegativeImbalance(refund));
+        }
+        let actual_fee = fee.saturating_sub(refund);
+        self.deposit_event(Event::TransactionFeePaid { who: who.clone(), actual_fee });
+        NegativeImbalance(actual_fee)
+    }
+
+    // Checks that the balances of all the accounts add up to the total issuance, that every
+    // account has at least the existential deposit, and that its frozen balance is its largest lock
+    // Run by tests after changing balances
+    #[cfg(test)]
+    pub fn assert_invariants(&self) {
+        let mut sum = T::Balance::zero();
+        for (who, account) in self.accounts.iter() {
+            assert!(!Self::is_dust(account.total()), "{:?} is below the existential deposit", who);
+            let frozen = self.locks(&who).into_iter().map(|lock| lock.amount).fold(T::Balance::zero(), max);
+            assert!(account.frozen == frozen, "{:?} has a frozen balance of {:?} for locks of {:?}", who, account.frozen, frozen);
+            sum = sum.checked_add(&account.total()).expect("balances overflow");
+        }
+        assert!(sum == self.total_issuance(), "balances add up to {:?}, total issuance is {:?}", sum, self.total_issuance());
+        for (who, _) in self.locks.iter() {
+            assert!(self.account_exists(&who), "{:?} has locks but no account", who);
+        }
+    }
+
+    // The storage key of the balance of `who`, and the proof of its value against the state root
+    // Checked with `support::trie::verify_read_proof`, e.g. by a client which only has the header
+    pub fn balance_proof(&self, who: &T::AccountId) -> (Vec<u8>, crate::support::trie::ReadProof) {
+        (self.accounts.hashed_key(who), self.accounts.read_proof(who))
+    }
+}
+
+impl<T: Config> Currency<T::AccountId> for Pallet<T> {
+    type Balance = T::Balance;
+    type NegativeImbalance = NegativeImbalance<T>;
+
+    fn total_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.account(who).total()
+    }
+
+    fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.balance(who)
+    }
+
+    fn total_issuance(&self) -> T::Balance {
+        Pallet::total_issuance(self)
+    }
+
+    fn minimum_balance(&self) -> T::Balance {
+        T::EXISTENTIAL_DEPOSIT
+    }
+
+    fn transfer(&mut self, from: &T::AccountId, to: &T::AccountId, amount: T::Balance,
+                existence: ExistenceRequirement) -> DispatchResult {
+        self.do_transfer(from.clone(), to.clone(), amount, existence).map(|_| ())
+    }
+
+    // Only funds which are not locked can be withdrawn, fees aside (see `withdraw_fee`)
+    fn withdraw(&mut self, who: &T::AccountId, amount: T::Balance,
+                existence: ExistenceRequirement) -> Result<NegativeImbalance<T>, DispatchError> {
+        let account = self.account(who);
+        if account.free >= amount && amount > account.usable() {
+            return Err(Error::LiquidityRestrictions.into());
+        }
+        self.do_withdraw(who, amount, existence)
+    }
+
+    // The free balance is slashed first, even if it is locked, then the reserved balance
+    fn slash(&mut self, who: &T::AccountId, amount: T::Balance) -> (NegativeImbalance<T>, T::Balance) {
+        let (slashed, _) = self.mutate_account(who, |account| {
+            let from_free = min(amount, account.free);
+            let from_reserved = min(amount.saturating_sub(from_free), account.reserved);
+            account.free = account.free.saturating_sub(from_free);
+            account.reserved = account.reserved.saturating_sub(from_reserved);
+            from_free.saturating_add(from_reserved)
+        });
+        (NegativeImbalance(slashed), amount.saturating_sub(slashed))
+    }
+
+    // The funds are burned if they can't create the account or would overflow its balance
+    fn resolve_creating(&mut self, who: &T::AccountId, imbalance: NegativeImbalance<T>) {
+        if let Some(balance) = self.balance(who).checked_add(&imbalance.peek()) {
+            self.set_balance(who, balance);
+        }
+    }
+}
+
+impl<T: Config> ReservableCurrency<T::AccountId> for Pallet<T> {
+    fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.account(who).reserved
+    }
+
+    // Only funds which are not locked can be reserved
+    fn can_reserve(&self, who: &T::AccountId, amount: T::Balance) -> bool {
+        amount <= self.account(who).usable()
+    }
+
+    fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let account = self.account(who);
+        let new_free = account.free.checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+        if amount > account.usable() {
+            return Err(Error::LiquidityRestrictions.into());
+        }
+        let new_reserved = account.reserved.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        self.mutate_account(who, |account| {
+            account.free = new_free;
+            account.reserved = new_reserved;
+        });
+        self.deposit_event(Event::Reserved { who: who.clone(), amount });
+        Ok(())
+    }
+
+    fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let actual = min(amount, self.reserved_balance(who));
+        if actual.is_zero() {
+            return amount;
+        }
+        self.mutate_account(who, |account| {
+            account.reserved = account.reserved.saturating_sub(actual);
+            account.free = account.free.saturating_add(actual);
+        });
+        self.deposit_event(Event::Unreserved { who: who.clone(), amount: actual });
+        amount.saturating_sub(actual)
+    }
+
+    fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> (NegativeImbalance<T>, T::Balance) {
+        let (slashed, _) = self.mutate_account(who, |account| {
+            let slashed = min(amount, account.reserved);
+            account.reserved = account.reserved.saturating_sub(slashed);
+            slashed
+        });
+        (NegativeImbalance(slashed), amount.saturating_sub(slashed))
+    }
+
+    fn repatriate_reserved(&mut self, slashed: &T::AccountId, beneficiary: &T::AccountId, amount: T::Balance,
+                           status: BalanceStatus) -> Result<T::Balance, DispatchError> {
+        if slashed == beneficiary {
+            return Ok(match status {
+                BalanceStatus::Free => self.unreserve(slashed, amount),
+                BalanceStatus::Reserved => amount.saturating_sub(min(amount, self.reserved_balance(slashed))),
+            });
+        }
+        let actual = min(amount, self.reserved_balance(slashed));
+        let beneficiary_total = self.account(beneficiary).total().checked_add(&actual)
+            .ok_or(ArithmeticError::Overflow)?;
+        if Self::is_dust(beneficiary_total) {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        self.mutate_account(slashed, |account| account.reserved = account.reserved.saturating_sub(actual));
+        self.mutate_account(beneficiary, |account| match status {
+            BalanceStatus::Free => account.free = account.free.saturating_add(actual),
+            BalanceStatus::Reserved => account.reserved = account.reserved.saturating_add(actual),
+        });
+        self.deposit_event(Event::ReserveRepatriated {
+            from: slashed.clone(),
+            to: beneficiary.clone(),
+            amount: actual,
+            status,
+        });
+        Ok(amount.saturating_sub(actual))
+    }
+}
+
+impl<T: Config> LockableCurrency<T::AccountId> for Pallet<T> {
+    // Nothing is locked on an account which doesn't exist
+    fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        let mut locks = self.locks(who);
+        match locks.iter_mut().find(|lock| lock.id == id) {
+            Some(lock) => lock.amount = amount,
+            None => locks.push(BalanceLock { id, amount }),
+        }
+        self.update_locks(who, locks);
+    }
+
+    fn extend_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        let amount = match self.locks(who).into_iter().find(|lock| lock.id == id) {
+            Some(lock) => max(lock.amount, amount),
+            None => amount,
+        };
+        self.set_lock(id, who, amount);
+    }
+
+    fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        let locks = self.locks(who).into_iter().filter(|lock| lock.id != id).collect();
+        self.update_locks(who, locks);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::system;
+    use crate::support::currency::{
+        BalanceStatus, Currency, ExistenceRequirement, Imbalance, LockableCurrency, ReservableCurrency,
+    };
+    use crate::support::storage::Storage;
+    use crate::support::{Dispatch, DispatchError, DispatchInfo, DispatchResult, GetDispatchInfo, InvalidTransaction};
+    use crate::system::{self, Origin};
 
+    #[derive(Debug, PartialEq)]
     struct TestConfig;
 
     impl system::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = super::Event<TestConfig>;
+        type RuntimeOrigin = system::Origin<String>;
+        const MAX_BLOCK_WEIGHT: u64 = 100;
     }
 
     impl super::Config for TestConfig {
         type Balance = u128;
+        const EXISTENTIAL_DEPOSIT: u128 = 10;
+    }
+
+    fn new_pallet() -> super::Pallet<TestConfig> {
+        super::Pallet::new(&Storage::default())
+    }
+
+    fn events(pallet: &super::Pallet<TestConfig>) -> Vec<super::Event<TestConfig>> {
+        let system = system::Pallet::<TestConfig>::new(&pallet.storage);
+        system.events().into_iter().map(|record| record.event).collect()
     }
 
     #[test]
     fn test_balance() {
-        let mut pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut pallet = new_pallet();
         const ALICE: &str = "Alice";
         const ANN: &str = "Ann";
         assert_eq!(pallet.balance(&ALICE.to_string()), 0);
@@ -72,25 +604,310 @@ mod test {
 
     #[test]
     fn transfer_balance() {
-        let mut pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut pallet = new_pallet();
         static ALICE: &str = "Alice";
         static BOB: &str = "Bob";
         pallet.set_balance(&ALICE.to_string(), 100);
         pallet.set_balance(&BOB.to_string(), 100);
-        pallet.transfer(ALICE.to_string(), BOB.to_string(), 50).unwrap();
+        pallet.transfer(Origin::Signed(ALICE.to_string()), BOB.to_string(), 50).unwrap();
         assert_eq!(pallet.balance(&ALICE.to_string()), 50);
         assert_eq!(pallet.balance(&BOB.to_string()), 150);
+        assert_eq!(events(&pallet), vec![super::Event::Transfer {
+            from: ALICE.to_string(),
+            to: BOB.to_string(),
+            amount: 50,
+        }]);
     }
 
     #[test]
     fn transfer_balance_insufficient() {
         //creat new mutable super::Pallet<AccountId, Balance>
-        let mut pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut pallet = new_pallet();
         const ALICE: &str = "Alice";
         const BOB: &str = "Bob";
         pallet.set_balance(&ALICE.to_string(), 100);
         pallet.set_balance(&BOB.to_string(), 100);
-        assert_eq!(pallet.transfer(ALICE.to_string(), BOB.to_string(), 150),
-                   Err("Insufficient balance"));
+        assert_eq!(pallet.transfer(Origin::Signed(ALICE.to_string()), BOB.to_string(), 150),
+                   Err(super::Error::InsufficientBalance.into()));
+        assert!(events(&pallet).is_empty());
+    }
+
+    #[test]
+    fn transfer_balance_overflow() {
+        let mut pallet = new_pallet();
+        const ALICE: &str = "Alice";
+        const BOB: &str = "Bob";
+        pallet.set_balance(&ALICE.to_string(), 100);
+        pallet.set_balance(&BOB.to_string(), u128::MAX);
+        assert_eq!(pallet.transfer(Origin::Signed(ALICE.to_string()), BOB.to_string(), 1),
+                   Err(crate::support::ArithmeticError::Overflow.into()));
+        assert_eq!(pallet.balance(&ALICE.to_string()), 100);
+    }
+
+    #[test]
+    fn transfer_to_self() {
+        let mut pallet = new_pallet();
+        let alice = "Alice".to_string();
+        pallet.set_balance(&alice, 100);
+        let call = super::Call::<TestConfig>::transfer { to: alice.clone(), amount: 50 };
+        assert_eq!(call.get_dispatch_info(), DispatchInfo { weight: 10 });
+
+        let post_info = pallet.dispatch(Origin::Signed(alice.clone()), call).unwrap();
+        assert_eq!(post_info.calc_actual_weight(&DispatchInfo { weight: 10 }), 2);
+        assert_eq!(pallet.balance(&alice), 100);
+        assert!(events(&pallet).is_empty());
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), alice, 150), Err(super::Error::InsufficientBalance.into()));
+    }
+
+    #[test]
+    fn force_set_balance() {
+        let mut pallet = new_pallet();
+        let alice = "Alice".to_string();
+        assert_eq!(pallet.force_set_balance(Origin::Signed(alice.clone()), alice.clone(), 100), Err(DispatchError::BadOrigin));
+        assert_eq!(pallet.force_set_balance(Origin::None, alice.clone(), 100), Err(DispatchError::BadOrigin));
+        assert_eq!(pallet.balance(&alice), 0);
+
+        assert_eq!(pallet.force_set_balance(Origin::Root, alice.clone(), 100), Ok(()));
+        assert_eq!(pallet.balance(&alice), 100);
+        assert_eq!(events(&pallet), vec![super::Event::BalanceSet { who: alice.clone(), free: 100 }]);
+        // Root can't transfer, it has no account
+        assert_eq!(pallet.transfer(Origin::Root, alice, 10), Err(DispatchError::BadOrigin));
+    }
+
+    #[test]
+    fn fees() {
+        let mut pallet = new_pallet();
+        let alice = "Alice".to_string();
+        pallet.set_balance(&alice, 100);
+        assert_eq!(pallet.withdraw_fee(&alice, 101), Err(InvalidTransaction::Payment));
+        // paying a fee can't reap the caller
+        assert_eq!(pallet.withdraw_fee(&alice, 91), Err(InvalidTransaction::Payment));
+        assert_eq!(pallet.withdraw_fee(&alice, 30), Ok(()));
+        assert_eq!(pallet.balance(&alice), 70);
+        assert_eq!(pallet.total_issuance(), 70);
+        // the fee actually paid is burned
+        let fee = pallet.refund_fee(&alice, 30, 10);
+        assert_eq!(fee.peek(), 20);
+        assert_eq!(pallet.balance(&alice), 80);
+        assert_eq!(pallet.total_issuance(), 80);
+        assert_eq!(events(&pallet), vec![super::Event::TransactionFeePaid { who: alice, actual_fee: 20 }]);
+    }
+
+    #[test]
+    fn accounts_below_existential_deposit_are_reaped() {
+        let mut pallet = new_pallet();
+        let (alice, bob, charlie) = ("Alice".to_string(), "Bob".to_string(), "Charlie".to_string());
+        pallet.set_balance(&alice, 100);
+        pallet.set_balance(&bob, 100);
+        // no zero balance is stored
+        pallet.set_balance(&charlie, 0);
+        assert!(!pallet.account_exists(&charlie));
+
+        // an account can't be created below the existential deposit
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), charlie.clone(), 9), Err(super::Error::ExistentialDeposit.into()));
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), charlie.clone(), 10), Ok(().into()));
+        assert!(pallet.account_exists(&charlie));
+
+        // bob is reaped along with his nonce, the 5 left are lost
+        let mut system = system::Pallet::<TestConfig>::new(&pallet.storage);
+        system.inc_nonce(&bob);
+        assert_eq!(pallet.transfer(Origin::Signed(bob.clone()), alice.clone(), 95), Ok(().into()));
+        assert!(!pallet.account_exists(&bob));
+        assert_eq!(pallet.balance(&bob), 0);
+        assert_eq!(pallet.balance(&alice), 185);
+        assert_eq!(system.get_nonce(&bob), 0);
+        assert_eq!(events(&pallet)[1..], [
+            super::Event::Transfer { from: bob.clone(), to: alice.clone(), amount: 95 },
+            super::Event::DustLost { account: bob, amount: 5 },
+        ]);
+
+        // Root setting a balance below the existential deposit reaps the account
+        assert_eq!(pallet.force_set_balance(Origin::Root, charlie.clone(), 3), Ok(()));
+        assert!(!pallet.account_exists(&charlie));
+        assert_eq!(events(&pallet).last(), Some(&super::Event::BalanceSet { who: charlie, free: 0 }));
+    }
+
+    #[test]
+    fn transfer_keep_alive() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.set_balance(&alice, 100);
+        assert_eq!(pallet.transfer_keep_alive(Origin::Signed(alice.clone()), bob.clone(), 91), Err(super::Error::KeepAlive.into()));
+        assert_eq!(pallet.transfer_keep_alive(Origin::Signed(alice.clone()), bob.clone(), 100), Err(super::Error::KeepAlive.into()));
+        assert_eq!(pallet.balance(&alice), 100);
+
+        assert_eq!(pallet.transfer_keep_alive(Origin::Signed(alice.clone()), bob.clone(), 90), Ok(().into()));
+        assert_eq!(pallet.balance(&alice), 10);
+        assert_eq!(pallet.balance(&bob), 90);
+    }
+
+    #[test]
+    fn total_issuance() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.build_genesis(&super::GenesisConfig { balances: vec![(alice.clone(), 100), (bob.clone(), 50)] });
+        assert_eq!(pallet.total_issuance(), 150);
+
+        // a transfer moves tokens, but the dust of a reaped account is lost
+        pallet.transfer(Origin::Signed(bob.clone()), alice.clone(), 45).unwrap();
+        assert_eq!(pallet.total_issuance(), 145);
+        pallet.assert_invariants();
+
+        assert_eq!(pallet.mint(Origin::Signed(alice.clone()), alice.clone(), 10), Err(DispatchError::BadOrigin));
+        assert_eq!(pallet.mint(Origin::Root, bob.clone(), 5), Err(super::Error::ExistentialDeposit.into()));
+        assert_eq!(pallet.mint(Origin::Root, bob.clone(), 20), Ok(()));
+        assert_eq!(pallet.mint(Origin::Root, alice.clone(), u128::MAX), Err(crate::support::ArithmeticError::Overflow.into()));
+        assert_eq!(pallet.total_issuance(), 165);
+
+        assert_eq!(pallet.burn(Origin::Root, bob.clone(), 21), Err(super::Error::InsufficientBalance.into()));
+        assert_eq!(pallet.burn(Origin::Root, alice.clone(), 45), Ok(()));
+        assert_eq!(pallet.force_set_balance(Origin::Root, bob.clone(), 30), Ok(()));
+        assert_eq!(pallet.balance(&alice), 100);
+        assert_eq!(pallet.total_issuance(), 130);
+        pallet.assert_invariants();
+        assert_eq!(events(&pallet)[2..], [
+            super::Event::Minted { who: bob.clone(), amount: 20 },
+            super::Event::Burned { who: alice, amount: 45 },
+            super::Event::BalanceSet { who: bob, free: 30 },
+        ]);
+    }
+
+    #[test]
+    fn imbalances() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.build_genesis(&super::GenesisConfig { balances: vec![(alice.clone(), 100), (bob.clone(), 100)] });
+
+        // what is withdrawn is gone from the total issuance until it is put somewhere
+        let imbalance = pallet.withdraw(&alice, 40, ExistenceRequirement::KeepAlive).unwrap();
+        assert_eq!(pallet.total_issuance(), 160);
+        let (to_bob, burned) = imbalance.split(30);
+        assert_eq!(burned.peek(), 10);
+        pallet.resolve_creating(&bob, to_bob);
+        assert_eq!(pallet.balance(&bob), 130);
+        assert_eq!(pallet.total_issuance(), 190);
+        assert_eq!(pallet.withdraw(&alice, 55, ExistenceRequirement::KeepAlive), Err(super::Error::KeepAlive.into()));
+
+        // a slash takes what it can
+        let (slashed, remaining) = pallet.slash(&alice, 70);
+        assert_eq!((slashed.peek(), remaining), (60, 10));
+        assert!(!pallet.account_exists(&alice));
+        // funds which can't create an account are burned
+        pallet.resolve_creating(&"Charlie".to_string(), slashed.split(5).0);
+        assert_eq!(pallet.total_issuance(), 130);
+        pallet.assert_invariants();
+    }
+
+    #[test]
+    fn reserves() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.build_genesis(&super::GenesisConfig { balances: vec![(alice.clone(), 100), (bob.clone(), 100)] });
+
+        assert_eq!(pallet.reserve(&alice, 101), Err(super::Error::InsufficientBalance.into()));
+        assert_eq!(pallet.reserve(&alice, 95), Ok(()));
+        assert_eq!(pallet.account(&alice), super::AccountData { free: 5, reserved: 95, frozen: 0 });
+        // reserved funds can't be transferred, but keep the account alive
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), bob.clone(), 10), Err(super::Error::InsufficientBalance.into()));
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), bob.clone(), 5), Ok(().into()));
+        assert!(pallet.account_exists(&alice));
+        assert_eq!(pallet.total_issuance(), 200);
+
+        assert_eq!(pallet.unreserve(&alice, 30), 0);
+        assert_eq!(pallet.account(&alice), super::AccountData { free: 30, reserved: 65, frozen: 0 });
+        // what is not reserved is left
+        assert_eq!(pallet.repatriate_reserved(&alice, &bob, 50, BalanceStatus::Reserved), Ok(0));
+        assert_eq!(pallet.repatriate_reserved(&alice, &bob, 20, BalanceStatus::Free), Ok(5));
+        assert_eq!(pallet.account(&alice), super::AccountData { free: 30, reserved: 0, frozen: 0 });
+        assert_eq!(pallet.account(&bob), super::AccountData { free: 120, reserved: 50, frozen: 0 });
+        assert_eq!(pallet.unreserve(&bob, 60), 10);
+        pallet.assert_invariants();
+        assert_eq!(events(&pallet)[..3], [
+            super::Event::Reserved { who: alice.clone(), amount: 95 },
+            super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 5 },
+            super::Event::Unreserved { who: alice.clone(), amount: 30 },
+        ]);
+        assert_eq!(events(&pallet)[3], super::Event::ReserveRepatriated {
+            from: alice,
+            to: bob,
+            amount: 50,
+            status: BalanceStatus::Reserved,
+        });
+    }
+
+    #[test]
+    fn reserved_balance_is_slashed() {
+        let mut pallet = new_pallet();
+        let alice = "Alice".to_string();
+        pallet.set_balance(&alice, 100);
+        pallet.reserve(&alice, 60).unwrap();
+
+        let (slashed, remaining) = pallet.slash_reserved(&alice, 70);
+        assert_eq!((slashed.peek(), remaining), (60, 10));
+        // a slash takes the free balance first
+        pallet.reserve(&alice, 20).unwrap();
+        let (slashed, _) = pallet.slash(&alice, 30);
+        assert_eq!(slashed.peek(), 30);
+        assert_eq!(pallet.account(&alice), super::AccountData { free: 0, reserved: 10, frozen: 0 });
+        assert_eq!(pallet.total_issuance(), 10);
+        pallet.assert_invariants();
+    }
+
+    #[test]
+    fn locks_restrict_transfers() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.set_balance(&alice, 100);
+        pallet.set_lock(*b"vesting ", &alice, 50);
+        pallet.set_lock(*b"staking ", &alice, 70);
+        assert_eq!(pallet.account(&alice).frozen, 70);
+
+        // locked funds can't be transferred, reserved nor withdrawn
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), bob.clone(), 31), Err(super::Error::LiquidityRestrictions.into()));
+        assert_eq!(pallet.reserve(&alice, 31), Err(super::Error::LiquidityRestrictions.into()));
+        assert_eq!(pallet.withdraw(&alice, 31, ExistenceRequirement::AllowDeath),
+                   Err(super::Error::LiquidityRestrictions.into()));
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), bob.clone(), 30), Ok(().into()));
+        // but they can pay fees
+        assert_eq!(pallet.withdraw_fee(&alice, 10), Ok(()));
+
+        pallet.extend_lock(*b"staking ", &alice, 10);
+        assert_eq!(pallet.account(&alice).frozen, 70);
+        pallet.remove_lock(*b"staking ", &alice);
+        assert_eq!(pallet.account(&alice).frozen, 50);
+        assert_eq!(pallet.transfer(Origin::Signed(alice.clone()), bob.clone(), 1), Ok(().into()));
+        pallet.remove_lock(*b"vesting ", &alice);
+        assert!(pallet.locks(&alice).is_empty());
+        assert_eq!(pallet.account(&alice).frozen, 0);
+
+        // an account which doesn't exist can't be locked
+        pallet.set_lock(*b"vesting ", &"Charlie".to_string(), 10);
+        assert!(pallet.locks(&"Charlie".to_string()).is_empty());
+        pallet.assert_invariants();
+    }
+
+    // Takes a deposit through the currency traits only, like a pallet depending on a currency
+    fn take_deposit<C: ReservableCurrency<String> + LockableCurrency<String>>(currency: &mut C, who: &String,
+                                                                             deposit: C::Balance) -> DispatchResult {
+        currency.set_lock(*b"deposit ", who, currency.minimum_balance());
+        currency.reserve(who, deposit)
+    }
+
+    #[test]
+    fn currency_traits() {
+        let mut pallet = new_pallet();
+        let (alice, bob) = ("Alice".to_string(), "Bob".to_string());
+        pallet.set_balance(&alice, 100);
+        assert_eq!(take_deposit(&mut pallet, &alice, 20), Ok(()));
+        assert_eq!(pallet.account(&alice), super::AccountData { free: 80, reserved: 20, frozen: 10 });
+        assert_eq!(Currency::total_balance(&pallet, &alice), 100);
+        assert!(!pallet.can_reserve(&alice, 71));
+
+        assert_eq!(Currency::transfer(&mut pallet, &alice, &bob, 70, ExistenceRequirement::KeepAlive), Ok(()));
+        assert_eq!(Currency::transfer(&mut pallet, &alice, &bob, 1, ExistenceRequirement::AllowDeath),
+                   Err(super::Error::LiquidityRestrictions.into()));
+        assert_eq!(pallet.free_balance(&bob), 70);
+        assert_eq!(Currency::total_issuance(&pallet), 100);
+        pallet.assert_invariants();
     }
 }
@@ -0,0 +1,199 @@
+use crate::support::crypto::{Hash, Pair};
+use crate::{balances, sudo, GenesisConfig, Runtime};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// The description of a chain: its name and its genesis state
+// Nodes started from the same chain spec build the same genesis state, so they agree on the
+// genesis hash, which identifies the chain
+// A chain spec is read from a JSON or TOML file, account ids being written in hex, e.g.
+//
+//   name = "Local Testnet"
+//   [genesis.balances]
+//   balances = [["0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d", 100]]
+//
+// Any pallet, or any field of a pallet, left out of the file keeps its default genesis config
+// TOML integers are 64 bits wide, so balances above `u64::MAX` can only be given in JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainSpec {
+    pub name: String,
+    #[serde(default)]
+    pub genesis: GenesisConfig,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // the file is not valid JSON or TOML, or doesn't describe a chain
+    Invalid(String),
+    // the file is neither `.json` nor `.toml`
+    UnknownFormat,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "Can't read the chain spec: {}", error),
+            Error::Invalid(error) => write!(f, "Invalid chain spec: {}", error),
+            Error::UnknownFormat => write!(f, "Chain spec must be a .json or a .toml file"),
+        }
+    }
+}
+
+impl ChainSpec {
+    // The chain used when no chain spec is given: alice is endowed with 1000 and holds the sudo key
+    pub fn development() -> Self {
+        let alice = Pair::from_phrase("//Alice").public();
+        let genesis = GenesisConfig {
+            balances: balances::GenesisConfig { balances: vec![(alice, 1000)] },
+            sudo: sudo::GenesisConfig { key: Some(alice) },
+            ..Default::default()
+        };
+        Self { name: "Development".to_string(), genesis }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::Invalid(e.to_string()))
+    }
+
+    // The TOML document is read as JSON values, since the TOML deserializer can't produce the
+    // 128 bits integers used for balances
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let value: toml::Value = toml::from_str(toml).map_err(|e| Error::Invalid(e.to_string()))?;
+        let value = serde_json::to_value(value).map_err(|e| Error::Invalid(e.to_string()))?;
+        serde_json::from_value(value).map_err(|e| Error::Invalid(e.to_string()))
+    }
+
+    // Reads a chain spec file, its format being given by its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(Error::Io)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&content),
+            Some("toml") => Self::from_toml(&content),
+            _ => Err(Error::UnknownFormat),
+        }
+    }
+
+    // A runtime with the genesis state of the chain, before its first block
+    pub fn build(&self) -> Runtime {
+        Runtime::from_genesis(&self.genesis)
+    }
+
+    // The hash of the genesis block, which commits to the whole genesis state
+    pub fn genesis_hash(&self) -> Hash {
+        self.build().system.genesis_hash()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChainSpec, Error};
+    use crate::support::crypto::ContentHash;
+
+    const JSON: &str = r#"{
+        "name": "Local Testnet",
+        "genesis": {
+            "system": { "block_number": 5 },
+            "balances": {
+                "balances": [
+                    ["0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d", 100],
+                    ["0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48", 50]
+                ]
+            },
+            "proof_of_existence": {
+                "claims": [["0xb5ecb7b1d5acb1ada1bb02bc77f9ade3a80b6b3e8b0f3518b5ae31037d075874", "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"]]
+            }
+        }
+    }"#;
+
+    const TOML: &str = r#"
+        name = "Local Testnet"
+
+        [genesis.system]
+        block_number = 5
+
+        [genesis.balances]
+        balances = [
+            ["0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d", 100],
+            ["0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48", 50],
+        ]
+
+        [genesis.proof_of_existence]
+        claims = [["0xb5ecb7b1d5acb1ada1bb02bc77f9ade3a80b6b3e8b0f3518b5ae31037d075874", "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"]]
+    "#;
+
+    #[test]
+    fn json_and_toml_specs_build_the_same_chain() {
+        let json = ChainSpec::from_json(JSON).unwrap();
+        let toml = ChainSpec::from_toml(TOML).unwrap();
+        assert_eq!(json, toml);
+        assert_eq!(json.genesis_hash(), toml.genesis_hash());
+
+        let runtime = json.build();
+        let alice = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d".parse().unwrap();
+        assert_eq!(runtime.system.block_number(), 5);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.proof_of_existence.get_claim(&ContentHash::of(b"genesis document")), Some(alice));
+        assert_eq!(runtime.system.genesis_hash(), runtime.system.parent_hash());
+    }
+
+    #[test]
+    fn genesis_hash_depends_on_the_genesis_state() {
+        let spec = ChainSpec::from_json(JSON).unwrap();
+        // the name of the chain is not part of its state
+        let renamed = ChainSpec { name: "Another Testnet".to_string(), ..spec.clone() };
+        assert_eq!(renamed.genesis_hash(), spec.genesis_hash());
+
+        let mut other = spec.clone();
+        other.genesis.balances.balances[1].1 = 51;
+        assert_ne!(other.genesis_hash(), spec.genesis_hash());
+        let mut other = spec.clone();
+        other.genesis.system.block_number = 0;
+        assert_ne!(other.genesis_hash(), spec.genesis_hash());
+    }
+
+    #[test]
+    fn large_balances() {
+        let json = JSON.replace("100]", &format!("{}]", u128::MAX));
+        let spec = ChainSpec::from_json(&json).unwrap();
+        assert_eq!(spec.genesis.balances.balances[0].1, u128::MAX);
+    }
+
+    #[test]
+    fn missing_pallets_keep_their_default_genesis() {
+        let spec = ChainSpec::from_toml("name = \"Empty\"").unwrap();
+        assert_eq!(spec.genesis, Default::default());
+        assert_eq!(spec.build().system.block_number(), 0);
+    }
+
+    #[test]
+    fn invalid_specs_are_rejected() {
+        // unknown pallet
+        assert!(matches!(ChainSpec::from_json(r#"{ "name": "x", "genesis": { "staking": {} } }"#), Err(Error::Invalid(_))));
+        // invalid account id
+        assert!(matches!(
+            ChainSpec::from_toml("name = \"x\"\n[genesis.balances]\nbalances = [[\"0x1234\", 1]]"),
+            Err(Error::Invalid(_))
+        ));
+        // not a TOML document
+        assert!(matches!(ChainSpec::from_toml("name = "), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn load_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = ChainSpec::from_json(JSON).unwrap();
+        for (file, content) in [("spec.json", JSON), ("spec.toml", TOML)] {
+            let path = dir.path().join(file);
+            std::fs::write(&path, content).unwrap();
+            assert_eq!(ChainSpec::load(&path).unwrap(), spec);
+        }
+
+        let path = dir.path().join("spec.yaml");
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(ChainSpec::load(&path), Err(Error::UnknownFormat)));
+        assert!(matches!(ChainSpec::load(dir.path().join("missing.json")), Err(Error::Io(_))));
+    }
+}
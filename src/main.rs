@@ -1,95 +1,1151 @@
-use crate::support::Dispatch;
+use crate::support::backend::{Backend, DiskBackend, MemoryBackend};
+use crate::support::crypto::{ContentHash, Pair};
+use crate::support::currency::{Currency, Imbalance};
+use crate::support::codec::{Decode, Encode};
+use crate::support::{Dispatch, UncheckedExtrinsic};
 
+mod assets;
+mod authorship;
 mod balances;
+mod chain_spec;
+#[cfg(test)]
+mod mock;
+mod node;
 mod system;
 mod support;
 mod proof_of_existence;
+mod sudo;
+mod vesting;
 
 mod types {
     use crate::support;
-    pub type AccountId = String;
+    pub type AccountId = support::crypto::AccountId32;
     pub type Balance = u128;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
-    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
+    pub type Extrinsic = support::UncheckedExtrinsic<crate::RuntimeCall, Nonce>;
     pub type Header = support::Header<BlockNumber>;
     pub type Block = support::Block<Header, Extrinsic>;
-    pub type Content = &'static str;
+    pub type AssetId = u32;
 }
 
 impl system::Config for Runtime {
     type AccountId = types::AccountId;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeOrigin = system::Origin<types::AccountId>;
+    // room for 10 transfers per block
+    const MAX_BLOCK_WEIGHT: support::Weight = 100;
 }
 
 impl balances::Config for Runtime {
     type Balance = types::Balance;
+    const EXISTENTIAL_DEPOSIT: types::Balance = 5;
 }
 
 impl proof_of_existence::Config for Runtime {
-    type Content = types::Content;
+    type MaxContentLength = support::bounded::ConstU32<1024>;
+    type MaxDescriptionLength = support::bounded::ConstU32<256>;
+    // removing them takes at most half of a block
+    type MaxExpiringPerBlock = support::bounded::ConstU32<10>;
+    type Currency = balances::Pallet<Runtime>;
+    const CLAIM_DEPOSIT: types::Balance = 10;
+    const DEPOSIT_PER_BYTE: types::Balance = 1;
 }
 
-#[derive(Debug)]
+impl authorship::Config for Runtime {}
+
+impl sudo::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl assets::Config for Runtime {
+    type AssetId = types::AssetId;
+    type Balance = types::Balance;
+    const STRING_LIMIT: usize = 32;
+    const MIN_BALANCE: types::Balance = 10;
+    const REMOVE_ITEMS_LIMIT: u32 = 50;
+}
+
+impl vesting::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    const MIN_VESTED_TRANSFER: types::Balance = 10;
+    const MAX_VESTING_SCHEDULES: usize = 4;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[macros::runtime]
 pub struct Runtime {
     system: system::Pallet::<Runtime>,
     balances: balances::Pallet<Runtime>,
     proof_of_existence: proof_of_existence::Pallet<Runtime>,
+    authorship: authorship::Pallet<Runtime>,
+    sudo: sudo::Pallet<Runtime>,
+    assets: assets::Pallet<Runtime>,
+    vesting: vesting::Pallet<Runtime>,
+}
+
+// Fees paid by the callers of extrinsics: a base fee for every extrinsic, and a fee for every unit
+// of weight of its call
+const BASE_FEE: types::Balance = 1;
+const WEIGHT_FEE: types::Balance = 1;
+
+fn weight_to_fee(weight: support::Weight) -> types::Balance {
+    weight as types::Balance * WEIGHT_FEE
+}
+
+impl support::OnChargeTransaction for Runtime {
+    type Caller = types::AccountId;
+
+    fn withdraw_fee(&mut self, who: &types::AccountId, info: &support::DispatchInfo) -> Result<(), support::InvalidTransaction> {
+        self.balances.withdraw_fee(who, BASE_FEE + weight_to_fee(info.weight))
+    }
+
+    fn refund_fee(&mut self, who: &types::AccountId, info: &support::DispatchInfo, actual_weight: support::Weight) {
+        let fee = BASE_FEE + weight_to_fee(info.weight);
+        let fee = self.balances.refund_fee(who, fee, weight_to_fee(info.weight - actual_weight));
+        // half of the fee goes to the author of the block, if it set itself, the rest is burned
+        if let Some(author) = self.authorship.author() {
+            let half = fee.peek() / 2;
+            let (to_author, _burned) = fee.split(half);
+            self.balances.resolve_creating(&author, to_author);
+        }
+    }
+}
+
+// Builds the next block of the chain on top of the last block imported by `runtime`
+// The block is executed once without keeping its changes, to find the state root it leads to
+// An invalid block keeps an empty state root, it is rejected anyway when executed
+fn build_block(runtime: &Runtime, timestamp: u64, extrinsics: Vec<types::Extrinsic>) -> types::Block {
+    let header = support::Header {
+        parent_hash: runtime.system.parent_hash(),
+        block_number: runtime.system.block_number() + 1,
+        state_root: Default::default(),
+        extrinsics_root: support::merkle::extrinsics_root(&extrinsics),
+        timestamp,
+    };
+    let mut block = types::Block { header, extrinsics };
+    block.header.state_root = runtime.compute_state_root(block.clone()).unwrap_or_default();
+    block
+}
+
+// Simulates sending a block over the wire: the block is encoded and decoded back
+fn transmit(block: types::Block) -> types::Block {
+    types::Block::decode_all(&block.encode()).expect("invalid block encoding")
+}
+
+// Prints the events emitted by the last executed block
+fn print_events(runtime: &Runtime) {
+    for record in runtime.system.events() {
+        println!("extrinsic #{}: {:?}", record.extrinsic_index, record.event);
+    }
 }
 
 fn main() {
-    // genesis block
-    let mut runtime = Runtime::new();
-
-    let alice = "Alice".to_string();
-    let bob = "Bob".to_string();
-    let charlie = "Charlie".to_string();
-
-    runtime.balances.set_balance(&alice, 100);
-
-    let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: bob.clone(),
-                    amount: 30
-                })
-            },
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: charlie.clone(),
-                    amount: 20
-                })
+    let alice = Pair::from_phrase("//Alice");
+    let bob = Pair::from_phrase("//Bob");
+    let charlie = Pair::from_phrase("//Charlie");
+
+    // usage: rsm-en [--chain <spec.json|spec.toml>] [--file <path>] [<database directory>]
+    // the chain is the development chain unless a chain spec file is given
+    // alice claims the file given with --file, by its hash, or else a document of her own
+    // the chain is kept in the database directory, or only in memory
+    // a node started again over the same directory goes on from its last imported block
+    let mut spec = chain_spec::ChainSpec::development();
+    let mut database = None;
+    let mut file = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--chain" => {
+                let path = args.next().expect("--chain expects a chain spec file");
+                spec = chain_spec::ChainSpec::load(path).unwrap_or_else(|e| panic!("can't load the chain spec: {}", e));
             }
-        ],
+            "--file" => file = Some(args.next().expect("--file expects a file to claim")),
+            _ => database = Some(arg),
+        }
+    }
+    let backend: Box<dyn Backend> = match database {
+        Some(path) => Box::new(DiskBackend::open(path).expect("can't open the database")),
+        None => Box::new(MemoryBackend::default()),
     };
+    let mut node = node::Node::open(backend, &spec)
+        .unwrap_or_else(|e| panic!("can't open the chain: {}", e));
+    println!("chain: {}", spec.name);
+    println!("starting at block #{} ({} blocks stored)",
+             node.runtime().system.block_number(), node.blocks().unwrap_or_else(|e| panic!("can't read the blocks: {}", e)).len());
 
-    runtime.execute_block(block_1).expect("wrong block execution");
+    // the genesis hash commits to the state of the chain before its first block
+    let genesis_hash = node.runtime().system.genesis_hash();
+    let alice_nonce = node.runtime().system.get_nonce(&alice.public());
+    let bob_nonce = node.runtime().system.get_nonce(&bob.public());
 
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "my document"
-                })
-            },
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "bob's document"
-                })
-            }
-        ],
+    let block_1 = build_block(node.runtime(), node.runtime().system.timestamp() + 6_000, vec![
+        // the author of the block sets itself with an inherent, which is not signed
+        UncheckedExtrinsic::new_unsigned(RuntimeCall::authorship(authorship::Call::set_author {
+            author: charlie.public(),
+        })),
+        UncheckedExtrinsic::new_signed(
+            &alice,
+            alice_nonce,
+            RuntimeCall::balances(balances::Call::transfer {
+                to: bob.public(),
+                amount: 50
+            }),
+            &genesis_hash,
+        ),
+        UncheckedExtrinsic::new_signed(
+            &alice,
+            alice_nonce + 1,
+            RuntimeCall::balances(balances::Call::transfer {
+                to: charlie.public(),
+                amount: 20
+            }),
+            &genesis_hash,
+        ),
+    ]);
+
+    node.import_block(transmit(block_1)).unwrap_or_else(|e| panic!("wrong block execution: {}", e));
+    print_events(node.runtime());
+    println!("block 1 authored by {:?}", node.runtime().authorship.author());
+
+    let alice_claim = match &file {
+        Some(path) => proof_of_existence::hash_file(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e)),
+        None => ContentHash::of(b"my document"),
     };
+    let block_2 = build_block(node.runtime(), node.runtime().system.timestamp() + 6_000, vec![
+        UncheckedExtrinsic::new_signed(
+            &alice,
+            alice_nonce + 2,
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: alice_claim,
+                description: Some(b"alice's document".to_vec().try_into().expect("the description is short enough")),
+                expires_at: None,
+            }),
+            &genesis_hash,
+        ),
+        UncheckedExtrinsic::new_signed(
+            &bob,
+            bob_nonce,
+            // a small content can be sent as is, and is hashed on chain
+            // bob's claim only attests the document for a while, it expires ten blocks after this one
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim_for_content {
+                content: b"bob's document".to_vec().try_into().expect("the content is small enough"),
+                description: None,
+                expires_at: Some(node.runtime().system.block_number() + 11),
+            }),
+            &genesis_hash,
+        ),
+        // alice holds the sudo key of the development chain, so she can dispatch calls as Root
+        UncheckedExtrinsic::new_signed(
+            &alice,
+            alice_nonce + 3,
+            RuntimeCall::sudo(sudo::Call::sudo {
+                call: Box::new(RuntimeCall::balances(balances::Call::force_set_balance {
+                    who: charlie.public(),
+                    new_balance: 50,
+                })),
+            }),
+            &genesis_hash,
+        ),
+    ]);
 
-    runtime.execute_block(block_2).expect("wrong block execution");
+    // anyone holding only the header can check that bob's claim was included in the block
+    let proof = block_2.extrinsic_proof(1).expect("extrinsic is in the block");
+    let included = support::merkle::verify_proof(
+        &block_2.header.extrinsics_root,
+        &block_2.extrinsics[1].encode(),
+        &proof,
+    );
+    println!("bob's claim included in block 2: {}", included);
+
+    let header_2 = block_2.header.clone();
+    node.import_block(transmit(block_2)).unwrap_or_else(|e| panic!("wrong block execution: {}", e));
+    print_events(node.runtime());
+    let runtime = node.runtime();
+
+    // anyone holding only the header can check the balance of charlie after block 2
+    let (key, proof) = runtime.balances.balance_proof(&charlie.public());
+    let proven = support::trie::verify_read_proof(
+        &header_2.state_root,
+        &key,
+        Some(&runtime.balances.account(&charlie.public()).encode()),
+        &proof,
+    );
+    println!("charlie's balance proven against block 2: {}", proven);
+
+    // bob hands his document over to charlie, the chain keeps bob as its previous owner
+    let bob_document = ContentHash::of(b"bob's document");
+    let block_3 = build_block(runtime, runtime.system.timestamp() + 6_000, vec![
+        UncheckedExtrinsic::new_signed(
+            &bob,
+            bob_nonce + 1,
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::transfer_claim {
+                claim: bob_document,
+                new_owner: charlie.public(),
+            }),
+            &genesis_hash,
+        ),
+    ]);
+    node.import_block(transmit(block_3)).unwrap_or_else(|e| panic!("wrong block execution: {}", e));
+    print_events(node.runtime());
+    let runtime = node.runtime();
+    println!("bob's document is owned by {:?}, previously by {:?}",
+             runtime.proof_of_existence.get_claim(&bob_document), runtime.proof_of_existence.claim_history(&bob_document));
+
+    if let Some(path) = &file {
+        match runtime.proof_of_existence.get_file_claim(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e)) {
+            Some(claim) => println!("{} was claimed by {:?} in block #{}", path, claim.owner, claim.block_number),
+            None => println!("{} is not claimed", path),
+        }
+    }
 
     println!("{:#?}", runtime);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use support::{crypto::AccountId32, storage::HasStorage, DispatchError, GetDispatchInfo, InvalidTransaction, ModuleError};
+
+    fn alice() -> AccountId32 {
+        Pair::from_phrase("//Alice").public()
+    }
+
+    fn bob() -> AccountId32 {
+        Pair::from_phrase("//Bob").public()
+    }
+
+    // Signs `call` with the key pair derived from `phrase`, for the chain of `runtime`
+    fn signed(runtime: &Runtime, phrase: &str, nonce: types::Nonce, call: RuntimeCall) -> types::Extrinsic {
+        let pair = Pair::from_phrase(phrase);
+        UncheckedExtrinsic::new_signed(&pair, nonce, call, &runtime.system.genesis_hash())
+    }
+
+    fn transfer(to: AccountId32, amount: types::Balance) -> RuntimeCall {
+        RuntimeCall::balances(balances::Call::transfer { to, amount })
+    }
+
+    fn create_claim(content: &str) -> RuntimeCall {
+        let claim = ContentHash::of(content.as_bytes());
+        RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim, description: None, expires_at: None })
+    }
+
+    // Builds the next block of `runtime`, produced 6 seconds after its parent
+    fn block(runtime: &Runtime, extrinsics: Vec<types::Extrinsic>) -> types::Block {
+        build_block(runtime, runtime.system.timestamp() + 6_000, extrinsics)
+    }
+
+    // The fee paid for `call` when it uses its whole weight
+    fn fee(call: &RuntimeCall) -> types::Balance {
+        BASE_FEE + weight_to_fee(call.get_dispatch_info().weight)
+    }
+
+    // The events of the last block, without the fee paid and the outcome recorded for each extrinsic
+    fn call_events(runtime: &Runtime) -> Vec<system::EventRecord<RuntimeEvent>> {
+        let is_call_event = |event: &RuntimeEvent| {
+            !matches!(event, RuntimeEvent::balances(balances::Event::TransactionFeePaid { .. }) | RuntimeEvent::system(_))
+        };
+        runtime.system.events().into_iter().filter(|record| is_call_event(&record.event)).collect()
+    }
+
+    fn runtime_with_claim() -> Runtime {
+        Runtime::from_genesis(&GenesisConfig {
+            balances: balances::GenesisConfig { balances: vec![(alice(), 100), (bob(), 100)] },
+            proof_of_existence: proof_of_existence::GenesisConfig {
+                claims: vec![(ContentHash::of(b"my document"), alice())],
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn failed_transaction_is_reverted() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+
+        // state is mutated in several pallets before the failing call
+        let res = support::with_transaction(&mut runtime, |runtime| {
+            runtime.balances.set_balance(&bob(), 10);
+            runtime.dispatch(system::Origin::Signed(alice()), create_claim("bob's document"))?;
+            runtime.dispatch(system::Origin::Signed(alice()), transfer(bob(), 500))
+        });
+
+        assert_eq!(res, Err(DispatchError::Module(ModuleError { index: 1, error: 0, message: None })));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn failed_claim_is_reverted() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+
+        let res = support::with_transaction(&mut runtime, |runtime| {
+            runtime.dispatch(system::Origin::Signed(alice()), transfer(bob(), 50))?;
+            runtime.dispatch(system::Origin::Signed(bob()), create_claim("my document"))
+        });
+
+        assert_eq!(res, Err(DispatchError::Module(ModuleError { index: 2, error: 0, message: None })));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn successful_transaction_is_committed() {
+        let mut runtime = runtime_with_claim();
+
+        let res = support::with_transaction(&mut runtime, |runtime| {
+            runtime.dispatch(system::Origin::Signed(alice()), transfer(bob(), 50))
+        });
+
+        assert!(res.is_ok());
+        assert_eq!(runtime.balances.balance(&alice()), 50);
+        assert_eq!(runtime.balances.balance(&bob()), 150);
+    }
+
+    #[test]
+    fn nested_transaction_reverts_only_inner_layer() {
+        let mut runtime = runtime_with_claim();
+
+        let res = support::with_transaction(&mut runtime, |runtime| {
+            runtime.balances.transfer(system::Origin::Signed(alice()), bob(), 30)?;
+            let inner = support::with_transaction(runtime, |runtime| {
+                runtime.proof_of_existence.create_claim(system::Origin::Signed(bob()), ContentHash::of(b"bob's document"), None, None)?;
+                runtime.balances.transfer(system::Origin::Signed(bob()), alice(), 1_000)
+            });
+            assert_eq!(inner, Err(balances::Error::InsufficientBalance.into()));
+            Ok::<(), DispatchError>(())
+        });
+
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.balances.balance(&alice()), 70);
+        assert_eq!(runtime.balances.balance(&bob()), 130);
+        assert_eq!(runtime.proof_of_existence.get_claim(&ContentHash::of(b"bob's document")), None);
+    }
+
+    #[test]
+    fn failed_extrinsic_in_block_leaves_no_trace() {
+        let mut runtime = runtime_with_claim();
+
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 150)),
+            signed(&runtime, "//Bob", 0, create_claim("my document")),
+        ]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.proof_of_existence.get_claim(&ContentHash::of(b"my document")), Some(alice()));
+        // the nonce and the fee are still consumed by the failed extrinsics
+        assert_eq!(runtime.balances.balance(&alice()), 100 - fee(&transfer(bob(), 150)));
+        assert_eq!(runtime.balances.balance(&bob()), 100 - fee(&create_claim("my document")));
+        assert_eq!(runtime.system.get_nonce(&alice()), 1);
+        assert_eq!(runtime.system.get_nonce(&bob()), 1);
+        // only the failure of each extrinsic is recorded
+        let outcomes = runtime.system.events().into_iter().filter(|record| {
+            !matches!(record.event, RuntimeEvent::balances(balances::Event::TransactionFeePaid { .. }))
+        });
+        assert_eq!(outcomes.collect::<Vec<_>>(), vec![
+            system::EventRecord {
+                extrinsic_index: 0,
+                event: RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                    error: DispatchError::from(balances::Error::InsufficientBalance).with_module_index(1),
+                }),
+            },
+            system::EventRecord {
+                extrinsic_index: 1,
+                event: RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                    error: DispatchError::from(proof_of_existence::Error::ClaimAlreadyExists).with_module_index(2),
+                }),
+            },
+        ]);
+    }
+
+    #[test]
+    fn module_errors_carry_pallet_index() {
+        let mut runtime = runtime_with_claim();
+
+        let res = runtime.dispatch(system::Origin::Signed(alice()), RuntimeCall::proof_of_existence(
+            proof_of_existence::Call::revoke_claim { claim: ContentHash::of(b"unknown document") }
+        ));
+        let Err(DispatchError::Module(error)) = res else {
+            panic!("expected a module error, got {:?}", res);
+        };
+        assert_eq!(error.index, 2);
+        assert_eq!(error.error, proof_of_existence::Error::NoClaimFound.index());
+        assert_eq!(error.message, Some("NoClaimFound"));
+    }
+
+    #[test]
+    fn events_are_recorded_per_block() {
+        let mut runtime = runtime_with_claim();
+
+        let block_1 = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 30)),
+            // fails, so it emits no event
+            signed(&runtime, "//Bob", 0, transfer(alice(), 500)),
+            signed(&runtime, "//Bob", 1, create_claim("bob's document")),
+        ]);
+        runtime.execute_block(block_1).unwrap();
+
+        assert_eq!(call_events(&runtime), vec![
+            system::EventRecord {
+                extrinsic_index: 0,
+                event: RuntimeEvent::balances(balances::Event::Transfer {
+                    from: alice(),
+                    to: bob(),
+                    amount: 30,
+                }),
+            },
+            // the deposit of the claim is reserved first
+            system::EventRecord {
+                extrinsic_index: 2,
+                event: RuntimeEvent::balances(balances::Event::Reserved { who: bob(), amount: 10 }),
+            },
+            system::EventRecord {
+                extrinsic_index: 2,
+                event: RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimCreated {
+                    who: bob(),
+                    claim: ContentHash::of(b"bob's document"),
+                }),
+            },
+        ]);
+
+        let block_2 = block(&runtime, vec![
+            signed(&runtime, "//Bob", 2, RuntimeCall::proof_of_existence(
+                proof_of_existence::Call::revoke_claim { claim: ContentHash::of(b"bob's document") }
+            )),
+        ]);
+        runtime.execute_block(block_2).unwrap();
+
+        assert_eq!(call_events(&runtime), vec![
+            system::EventRecord {
+                extrinsic_index: 0,
+                event: RuntimeEvent::balances(balances::Event::Unreserved { who: bob(), amount: 10 }),
+            },
+            system::EventRecord {
+                extrinsic_index: 0,
+                event: RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimRevoked {
+                    who: bob(),
+                    claim: ContentHash::of(b"bob's document"),
+                }),
+            },
+        ]);
+    }
+
+    #[test]
+    fn sequential_nonces_are_accepted() {
+        let mut runtime = runtime_with_claim();
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Alice", 1, transfer(bob(), 10)),
+        ]);
+        assert_eq!(runtime.execute_block(block), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob()), 120);
+        assert_eq!(runtime.system.get_nonce(&alice()), 2);
+    }
+
+    #[test]
+    fn replayed_extrinsic_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let block_1 = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        runtime.execute_block(block_1).unwrap();
+        let before = runtime.storage().state_root();
+
+        // the same extrinsic is included again in the next block
+        let block_2 = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        assert_eq!(runtime.execute_block(block_2), Err(InvalidTransaction::Stale.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+        assert_eq!(runtime.balances.balance(&bob()), 110);
+    }
+
+    #[test]
+    fn nonce_gap_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 1, transfer(bob(), 10))]);
+        assert_eq!(runtime.execute_block(block), Err(InvalidTransaction::Future.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn duplicate_nonce_in_block_rejects_whole_block() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Alice", 0, transfer(bob(), 20)),
+        ]);
+        assert_eq!(runtime.execute_block(block), Err(InvalidTransaction::Stale.into()));
+        // the first, valid, extrinsic is reverted together with the rest of the block
+        assert_eq!(runtime.storage().state_root(), before);
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn tampered_call_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let mut extrinsic = signed(&runtime, "//Alice", 0, transfer(bob(), 10));
+        extrinsic.call = transfer(bob(), 90);
+
+        assert_eq!(runtime.execute_block(block(&runtime, vec![extrinsic])), Err(InvalidTransaction::BadProof.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn impersonation_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        // Bob signs a transfer but claims to be Alice
+        let mut extrinsic = signed(&runtime, "//Bob", 0, transfer(bob(), 10));
+        extrinsic.signature.as_mut().unwrap().signer = alice();
+
+        assert_eq!(runtime.execute_block(block(&runtime, vec![extrinsic])), Err(InvalidTransaction::BadProof.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn extrinsic_for_another_chain_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let other_genesis_hash = support::crypto::blake2_256(b"another chain");
+        let extrinsic = UncheckedExtrinsic::new_signed(
+            &Pair::from_phrase("//Alice"), 0, transfer(bob(), 10), &other_genesis_hash,
+        );
+
+        assert_eq!(runtime.execute_block(block(&runtime, vec![extrinsic])), Err(InvalidTransaction::BadProof.into()));
+    }
+
+    #[test]
+    fn calls_use_stable_indices() {
+        let call = transfer(bob(), 1);
+        let encoded = call.encode();
+        // balances is the pallet 1, transfer is its call 0
+        assert_eq!(&encoded[..2], &[1, 0]);
+        assert_eq!(&encoded[2..34], &bob().0);
+        assert_eq!(&encoded[34..], &1u128.to_le_bytes());
+        assert_eq!(RuntimeCall::decode_all(&encoded), Ok(call));
+
+        let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+            claim: ContentHash::of(b"doc"),
+        });
+        assert_eq!(call.encode(), [vec![2, 1], ContentHash::of(b"doc").0.to_vec()].concat());
+        // a call given to sudo is encoded in place, after the indices of sudo
+        let sudo = RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(call.clone()) });
+        assert_eq!(sudo.encode(), [vec![4, 0], call.encode()].concat());
+        assert_eq!(RuntimeCall::decode_all(&sudo.encode()), Ok(sudo));
+        assert_eq!(RuntimeCall::decode_all(&[7, 0]), Err(support::codec::Error("invalid pallet index")));
+    }
+
+    #[test]
+    fn deeply_nested_calls_are_rejected() {
+        // a sudo call holding a sudo call holding ..., a million times
+        let call = transfer(bob(), 10).encode();
+        let nested = [[4u8, 0].repeat(1_000_000), call.clone()].concat();
+        assert_eq!(RuntimeCall::decode_all(&nested), Err(support::codec::Error("too deeply nested")));
+
+        // nor can a peer send it in a block, the call being the end of its last extrinsic
+        let runtime = runtime_with_claim();
+        let block = block(&runtime, vec![UncheckedExtrinsic::new_unsigned(transfer(bob(), 10))]);
+        let encoded = block.encode();
+        let encoded = [&encoded[..encoded.len() - call.len()], &nested].concat();
+        assert_eq!(types::Block::decode_all(&encoded), Err(support::codec::Error("too deeply nested")));
+    }
+
+    #[test]
+    fn decoded_block_is_identical() {
+        let mut runtime = runtime_with_claim();
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Bob", 0, create_claim("bob's document")),
+        ]);
+        let encoded = block.encode();
+        let decoded = types::Block::decode_all(&encoded).unwrap();
+
+        assert_eq!(decoded, block);
+        assert_eq!(decoded.encode(), encoded);
+        // signatures are still valid after the round trip
+        assert_eq!(runtime.execute_block(decoded), Ok(()));
+        // the deposit of the claim is reserved, out of the free balance
+        assert_eq!(runtime.balances.balance(&bob()), 110 - fee(&create_claim("bob's document")) - 10);
+    }
+
+    #[test]
+    fn events_and_errors_round_trip() {
+        let event = RuntimeEvent::balances(balances::Event::Transfer { from: alice(), to: bob(), amount: 5 });
+        assert_eq!(RuntimeEvent::decode_all(&event.encode()), Ok(event));
+
+        let error: DispatchError = balances::Error::InsufficientBalance.into();
+        let error = error.with_module_index(1);
+        // module errors are encoded compactly, without their message
+        assert_eq!(error.encode(), vec![0, 1, 0]);
+        assert_eq!(DispatchError::decode_all(&error.encode()), Ok(error));
+        let error: DispatchError = InvalidTransaction::Stale.into();
+        assert_eq!(DispatchError::decode_all(&error.encode()), Ok(error));
+    }
+
+    #[test]
+    fn blocks_are_chained() {
+        let mut runtime = runtime_with_claim();
+        let genesis_hash = runtime.system.genesis_hash();
+        // the genesis header commits to the state the chain starts from
+        let genesis = runtime.system.last_header();
+        assert_eq!((genesis.block_number, genesis.parent_hash), (0, [0; 32]));
+        assert_eq!(genesis.state_root, runtime.storage().state_root());
+        assert_eq!(genesis_hash, genesis.hash());
+
+        let block_1 = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        let hash_1 = block_1.header.hash();
+        assert_eq!(block_1.header.parent_hash, genesis_hash);
+        runtime.execute_block(block_1).unwrap();
+        assert_eq!(runtime.system.block_hash(1), Some(hash_1));
+        assert_eq!(runtime.system.parent_hash(), hash_1);
+
+        let block_2 = block(&runtime, vec![]);
+        assert_eq!(block_2.header.parent_hash, hash_1);
+        runtime.execute_block(block_2).unwrap();
+        assert_eq!(runtime.system.block_number(), 2);
+        assert_eq!(runtime.system.timestamp(), 12_000);
+    }
+
+    #[test]
+    fn block_with_wrong_parent_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let block_1 = block(&runtime, vec![]);
+        runtime.execute_block(block_1.clone()).unwrap();
+        let before = runtime.storage().state_root();
+
+        // a sibling of block 1, built on top of genesis, can't be imported as block 2
+        let mut fork = block_1;
+        fork.header.block_number = 2;
+        assert_eq!(
+            runtime.execute_block(fork),
+            Err(DispatchError::Other("parent hash does not match the last imported block"))
+        );
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn block_from_the_past_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        runtime.execute_block(block(&runtime, vec![])).unwrap();
+
+        let mut block_2 = block(&runtime, vec![]);
+        block_2.header.timestamp = 1_000;
+        assert_eq!(
+            runtime.execute_block(block_2),
+            Err(DispatchError::Other("block timestamp is earlier than the timestamp of its parent"))
+        );
+    }
+
+    #[test]
+    fn header_hash_commits_to_every_field() {
+        let header = types::Header {
+            parent_hash: [0; 32],
+            block_number: 0,
+            state_root: [0; 32],
+            extrinsics_root: [0; 32],
+            timestamp: 0,
+        };
+        let mut other = header.clone();
+        other.timestamp = 1;
+        assert_ne!(header.hash(), other.hash());
+        let mut other = header.clone();
+        other.state_root = [1; 32];
+        assert_ne!(header.hash(), other.hash());
+        let mut other = header.clone();
+        other.extrinsics_root = [1; 32];
+        assert_ne!(header.hash(), other.hash());
+    }
+
+    #[test]
+    fn altered_extrinsics_are_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let mut block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Alice", 1, transfer(bob(), 20)),
+        ]);
+        // dropping a validly signed extrinsic is detected too
+        block.extrinsics.pop();
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err(DispatchError::Other("extrinsics root does not match the extrinsics of the block"))
+        );
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn extrinsic_inclusion_proof() {
+        let runtime = runtime_with_claim();
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Alice", 1, transfer(bob(), 20)),
+            signed(&runtime, "//Bob", 0, create_claim("bob's document")),
+        ]);
+        // a light client only knows the header, the extrinsic and the proof
+        let header = block.header.clone();
+        let proof = block.extrinsic_proof(2).unwrap();
+        let extrinsic = block.extrinsics[2].encode();
+
+        assert!(support::merkle::verify_proof(&header.extrinsics_root, &extrinsic, &proof));
+        assert!(!support::merkle::verify_proof(&header.extrinsics_root, &block.extrinsics[1].encode(), &proof));
+        assert_eq!(block.extrinsic_proof(3), None);
+    }
+
+    #[test]
+    fn wrong_state_root_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let mut block = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        block.header.state_root[0] ^= 1;
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err(DispatchError::Other("state root does not match the state after the block"))
+        );
+        assert_eq!(runtime.storage().state_root(), before);
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn state_root_matches_header() {
+        let mut runtime = runtime_with_claim();
+        let block_1 = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, transfer(bob(), 10)),
+            signed(&runtime, "//Bob", 0, create_claim("bob's document")),
+        ]);
+        let empty_block = block(&runtime, vec![]);
+        // building blocks doesn't change the state
+        assert_eq!(runtime.system.block_number(), 0);
+        // the state root commits to the changes of every pallet
+        assert_ne!(block_1.header.state_root, empty_block.header.state_root);
+
+        let state_root = block_1.header.state_root;
+        runtime.execute_block(block_1).unwrap();
+        assert_eq!(runtime.storage().state_root(), state_root);
+        assert_eq!(runtime.system.last_header().state_root, state_root);
+    }
+
+    #[test]
+    fn balance_read_proof() {
+        let mut runtime = runtime_with_claim();
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        let header = block.header.clone();
+        runtime.execute_block(block).unwrap();
+
+        // a light client only knows the header, the storage key and the proof
+        let (key, proof) = runtime.balances.balance_proof(&bob());
+        let verify = |value: Option<&[u8]>| support::trie::verify_read_proof(&header.state_root, &key, value, &proof);
+        let account = |free| balances::AccountData::<types::Balance> { free, reserved: 0, frozen: 0 }.encode();
+        assert!(verify(Some(&account(110))));
+        assert!(!verify(Some(&account(111))));
+        assert!(!verify(None));
+
+        // the absence of a balance can be proven too
+        let charlie = Pair::from_phrase("//Charlie").public();
+        let (key, proof) = runtime.balances.balance_proof(&charlie);
+        assert!(support::trie::verify_read_proof(&header.state_root, &key, None, &proof));
+    }
+
+    #[test]
+    fn fee_is_paid_before_dispatch() {
+        let mut runtime = runtime_with_claim();
+        let call = transfer(bob(), 30);
+        assert_eq!(call.get_dispatch_info().weight, 10);
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, call.clone())]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&alice()), 70 - fee(&call));
+        assert_eq!(runtime.balances.balance(&bob()), 130);
+        assert_eq!(runtime.system.block_weight(), 10);
+        let events = runtime.system.events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+        assert_eq!(events[events.len() - 2..], [
+            RuntimeEvent::balances(balances::Event::TransactionFeePaid { who: alice(), actual_fee: fee(&call) }),
+            RuntimeEvent::system(system::Event::ExtrinsicSuccess),
+        ]);
+    }
+
+    #[test]
+    fn unused_weight_is_refunded() {
+        let mut runtime = runtime_with_claim();
+        // a transfer to oneself only reads a balance
+        let call = transfer(alice(), 30);
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, call)]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&alice()), 100 - BASE_FEE - weight_to_fee(2));
+        assert_eq!(runtime.system.block_weight(), 2);
+    }
+
+    #[test]
+    fn caller_unable_to_pay_is_rejected() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        let charlie = Pair::from_phrase("//Charlie").public();
+        let block = block(&runtime, vec![signed(&runtime, "//Charlie", 0, create_claim("doc"))]);
+
+        assert_eq!(runtime.execute_block(block), Err(InvalidTransaction::Payment.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+        assert_eq!(runtime.system.get_nonce(&charlie), 0);
+    }
+
+    #[test]
+    fn block_weight_is_limited() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        // 10 transfers fill the block, alice and bob taking turns
+        let extrinsics = (0..11)
+            .map(|i| match i % 2 {
+                0 => signed(&runtime, "//Alice", i / 2, transfer(bob(), 1)),
+                _ => signed(&runtime, "//Bob", i / 2, transfer(alice(), 1)),
+            })
+            .collect::<Vec<_>>();
+        let full_block = block(&runtime, extrinsics[..10].to_vec());
+        let overweight_block = block(&runtime, extrinsics);
+
+        assert_eq!(runtime.execute_block(overweight_block), Err(InvalidTransaction::ExhaustsResources.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+        assert_eq!(runtime.execute_block(full_block), Ok(()));
+        assert_eq!(runtime.system.block_weight(), <Runtime as system::Config>::MAX_BLOCK_WEIGHT);
+    }
+
+    fn set_author(author: AccountId32) -> types::Extrinsic {
+        UncheckedExtrinsic::new_unsigned(RuntimeCall::authorship(authorship::Call::set_author { author }))
+    }
+
+    #[test]
+    fn inherent_is_dispatched_without_origin() {
+        let mut runtime = runtime_with_claim();
+        let block = block(&runtime, vec![set_author(bob()), signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.authorship.author(), Some(bob()));
+        // an inherent pays no fee and uses no nonce, bob only gets his share of the fee of alice
+        assert_eq!(runtime.balances.balance(&bob()), 110 + fee(&transfer(bob(), 10)) / 2);
+        assert_eq!(runtime.system.get_nonce(&bob()), 0);
+        assert_eq!(runtime.system.block_weight(), 12);
+    }
+
+    #[test]
+    fn failed_inherent_rejects_block() {
+        let mut runtime = runtime_with_claim();
+        let before = runtime.storage().state_root();
+        // the author can only be set once
+        let block_1 = block(&runtime, vec![set_author(bob()), set_author(alice())]);
+        assert_eq!(runtime.execute_block(block_1), Err(InvalidTransaction::BadInherent.into()));
+        // calls made for signed extrinsics can't be sent unsigned
+        let block_2 = block(&runtime, vec![UncheckedExtrinsic::new_unsigned(transfer(bob(), 10))]);
+        assert_eq!(runtime.execute_block(block_2), Err(InvalidTransaction::BadInherent.into()));
+        assert_eq!(runtime.storage().state_root(), before);
+    }
+
+    #[test]
+    fn signed_origin_is_not_root() {
+        let mut runtime = runtime_with_claim();
+        let call = RuntimeCall::balances(balances::Call::force_set_balance { who: alice(), new_balance: 1_000 });
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, call.clone())]);
+        runtime.execute_block(block).unwrap();
+        // the call fails, but its fee is paid
+        assert_eq!(runtime.balances.balance(&alice()), 100 - fee(&call));
+
+        assert!(runtime.dispatch(system::Origin::Root, call).is_ok());
+        assert_eq!(runtime.balances.balance(&alice()), 1_000);
+    }
+
+    fn sudo(call: RuntimeCall) -> RuntimeCall {
+        RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(call) })
+    }
+
+    // The runtime of `runtime_with_claim`, alice holding the sudo key
+    fn runtime_with_sudo() -> Runtime {
+        let mut runtime = runtime_with_claim();
+        runtime.sudo.build_genesis(&sudo::GenesisConfig { key: Some(alice()) });
+        runtime
+    }
+
+    #[test]
+    fn sudo_key_dispatches_as_root() {
+        let mut runtime = runtime_with_sudo();
+        let call = sudo(RuntimeCall::balances(balances::Call::force_set_balance { who: bob(), new_balance: 1_000 }));
+        // sudo is charged for the weight of the call it dispatches
+        assert_eq!(call.get_dispatch_info().weight, 10);
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, call.clone())]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&bob()), 1_000);
+        assert_eq!(runtime.balances.balance(&alice()), 100 - fee(&call));
+        let events = call_events(&runtime).into_iter().map(|record| record.event).collect::<Vec<_>>();
+        assert_eq!(events, vec![
+            RuntimeEvent::balances(balances::Event::BalanceSet { who: bob(), free: 1_000 }),
+            RuntimeEvent::sudo(sudo::Event::Sudid { sudo_result: Ok(()) }),
+        ]);
+    }
+
+    #[test]
+    fn only_sudo_key_can_sudo() {
+        let mut runtime = runtime_with_sudo();
+        let call = sudo(RuntimeCall::balances(balances::Call::force_set_balance { who: bob(), new_balance: 1_000 }));
+        assert_eq!(
+            runtime.dispatch(system::Origin::Signed(bob()), call.clone()),
+            Err(DispatchError::Module(ModuleError { index: 4, error: 0, message: None }))
+        );
+
+        // once the key is given to bob, only bob can sudo
+        let set_key = RuntimeCall::sudo(sudo::Call::set_key { new: bob() });
+        assert!(runtime.dispatch(system::Origin::Signed(alice()), set_key).is_ok());
+        assert!(runtime.dispatch(system::Origin::Signed(alice()), call.clone()).is_err());
+        assert!(runtime.dispatch(system::Origin::Signed(bob()), call).is_ok());
+        assert_eq!(runtime.balances.balance(&bob()), 1_000);
+    }
+
+    #[test]
+    fn failed_sudo_call_is_reverted() {
+        let mut runtime = runtime_with_sudo();
+        // a transfer needs a signed origin, so it fails when dispatched as Root
+        let call = sudo(transfer(bob(), 10));
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, call.clone())]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&bob()), 100);
+        assert_eq!(runtime.balances.balance(&alice()), 100 - fee(&call));
+        assert_eq!(call_events(&runtime)[0].event, RuntimeEvent::sudo(sudo::Event::Sudid {
+            sudo_result: Err(DispatchError::BadOrigin),
+        }));
+    }
+
+    #[test]
+    fn reaped_account_loses_its_nonce() {
+        let mut runtime = runtime_with_claim();
+        let call = transfer(alice(), 85);
+        // bob is left with 100 - 85 - 11 = 4, below the existential deposit
+        assert_eq!(fee(&call), 11);
+        let block = block(&runtime, vec![signed(&runtime, "//Bob", 0, call)]);
+        runtime.execute_block(block).unwrap();
+
+        assert!(!runtime.balances.account_exists(&bob()));
+        assert_eq!(runtime.system.get_nonce(&bob()), 0);
+        assert_eq!(runtime.balances.balance(&alice()), 185);
+        assert!(call_events(&runtime).iter().any(|record| {
+            record.event == RuntimeEvent::balances(balances::Event::DustLost { account: bob(), amount: 4 })
+        }));
+    }
+
+    #[test]
+    fn fees_are_shared_with_the_author_and_burned() {
+        let mut runtime = runtime_with_claim();
+        let charlie = Pair::from_phrase("//Charlie").public();
+        assert_eq!(runtime.balances.total_issuance(), 200);
+        let call = transfer(bob(), 10);
+        let block_1 = block(&runtime, vec![signed(&runtime, "//Alice", 0, call.clone())]);
+        runtime.execute_block(block_1).unwrap();
+        // without an author, the whole fee is burned
+        assert_eq!(runtime.balances.total_issuance(), 200 - fee(&call));
+        runtime.balances.assert_invariants();
+
+        let block_2 = block(&runtime, vec![set_author(charlie), signed(&runtime, "//Alice", 1, call.clone())]);
+        runtime.execute_block(block_2).unwrap();
+        assert_eq!(runtime.balances.balance(&charlie), fee(&call) / 2);
+        assert_eq!(runtime.balances.total_issuance(), 200 - fee(&call) - (fee(&call) - fee(&call) / 2));
+        runtime.balances.assert_invariants();
+    }
+
+    #[test]
+    fn sudo_mints_and_burns() {
+        let mut runtime = runtime_with_sudo();
+        let mint = sudo(RuntimeCall::balances(balances::Call::mint { who: bob(), amount: 50 }));
+        let burn = sudo(RuntimeCall::balances(balances::Call::burn { who: bob(), amount: 20 }));
+        let block = block(&runtime, vec![signed(&runtime, "//Alice", 0, mint.clone()), signed(&runtime, "//Alice", 1, burn.clone())]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&bob()), 130);
+        assert_eq!(runtime.balances.total_issuance(), 230 - fee(&mint) - fee(&burn));
+        runtime.balances.assert_invariants();
+    }
+
+    #[test]
+    fn assets_are_issued_and_transferred() {
+        let mut runtime = runtime_with_claim();
+        let create = RuntimeCall::assets(assets::Call::create { id: 7, admin: alice() });
+        let mint = RuntimeCall::assets(assets::Call::mint { id: 7, beneficiary: alice(), amount: 1_000 });
+        let transfer = RuntimeCall::assets(assets::Call::transfer { id: 7, to: bob(), amount: 400 });
+        let block = block(&runtime, vec![
+            signed(&runtime, "//Alice", 0, create),
+            signed(&runtime, "//Alice", 1, mint),
+            signed(&runtime, "//Alice", 2, transfer),
+        ]);
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.assets.balance(&7, &alice()), 600);
+        assert_eq!(runtime.assets.balance(&7, &bob()), 400);
+        assert_eq!(runtime.assets.details(&7).unwrap().supply, 1_000);
+        // assets are apart from the native balances, which only pay for the fees
+        assert_eq!(runtime.balances.balance(&bob()), 100);
+    }
+
+    #[test]
+    fn vested_funds_are_released_block_after_block() {
+        let mut runtime = runtime_with_claim();
+        let schedule = vesting::VestingInfo { locked: 50, per_block: 10, starting_block: 1 };
+        let vested_transfer = RuntimeCall::vesting(vesting::Call::vested_transfer { target: bob(), schedule });
+        let block_1 = block(&runtime, vec![signed(&runtime, "//Alice", 0, vested_transfer)]);
+        runtime.execute_block(block_1).unwrap();
+        assert_eq!(runtime.balances.balance(&bob()), 150);
+        assert_eq!(runtime.balances.account(&bob()).frozen, 50);
+
+        // at block 3, two blocks of the schedule are vested, and the fee is paid with locked funds
+        let vest = RuntimeCall::vesting(vesting::Call::vest {});
+        let block_2 = block(&runtime, vec![]);
+        runtime.execute_block(block_2).unwrap();
+        let block_3 = block(&runtime, vec![signed(&runtime, "//Bob", 0, vest.clone())]);
+        runtime.execute_block(block_3).unwrap();
+        assert_eq!(runtime.balances.account(&bob()).frozen, 30);
+        assert_eq!(runtime.balances.balance(&bob()), 150 - fee(&vest));
+        runtime.balances.assert_invariants();
+    }
+
+    #[test]
+    fn expired_claims_are_removed_when_the_block_is_finalized() {
+        let mut runtime = runtime_with_claim();
+        let claim = ContentHash::of(b"bob's document");
+        let create_claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim,
+            description: None,
+            expires_at: Some(2),
+        });
+        let block_1 = block(&runtime, vec![signed(&runtime, "//Bob", 0, create_claim.clone())]);
+        runtime.execute_block(block_1).unwrap();
+        assert_eq!(runtime.balances.account(&bob()).reserved, 10);
+
+        // the claim is removed by the finalization hook of the pallet, after the extrinsics of block 2
+        let block_2 = block(&runtime, vec![signed(&runtime, "//Alice", 0, transfer(bob(), 10))]);
+        runtime.execute_block(block_2).unwrap();
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim), None);
+        assert_eq!(runtime.balances.account(&bob()).reserved, 0);
+        assert_eq!(runtime.balances.balance(&bob()), 110 - fee(&create_claim));
+        // the weight of removing it was reserved from the block
+        let transfer_weight = transfer(bob(), 10).get_dispatch_info().weight;
+        assert_eq!(runtime.system.block_weight(), transfer_weight + proof_of_existence::EXPIRE_CLAIM_WEIGHT);
+        assert_eq!(call_events(&runtime).pop(), Some(system::EventRecord {
+            extrinsic_index: 1,
+            event: RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimExpired { who: bob(), claim }),
+        }));
+    }
+
+    // Reserves a deposit through a currency reached over the storage of the runtime, the way a
+    // pallet reaches the currency set in its `Config`
+    fn reserve_deposit<C: support::currency::ReservableCurrency<AccountId32> + support::FromStorage>(
+        storage: &support::storage::Storage,
+        who: &AccountId32,
+        deposit: C::Balance,
+    ) -> support::DispatchResult {
+        C::from_storage(storage).reserve(who, deposit)
+    }
+
+    #[test]
+    fn pallets_reach_each_other_over_the_runtime_storage() {
+        let runtime = runtime_with_claim();
+        assert_eq!(reserve_deposit::<balances::Pallet<Runtime>>(runtime.storage(), &alice(), 30), Ok(()));
+        assert_eq!(runtime.balances.account(&alice()), balances::AccountData { free: 70, reserved: 30, frozen: 0 });
+        runtime.balances.assert_invariants();
+    }
+}
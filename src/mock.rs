@@ -0,0 +1,64 @@
+use crate::support::storage::Storage;
+use crate::support::FromStorage;
+use crate::{balances, proof_of_existence, system, vesting};
+
+// The test config of the pallets built on the balances pallet, shared by their tests
+// - each of those pallets implements its own config for `TestConfig` in its tests
+#[derive(Debug, PartialEq)]
+pub struct TestConfig;
+
+// The events of the balances pallet and of the pallets using it
+#[derive(Debug, PartialEq, macros::Encode, macros::Decode)]
+pub enum TestEvent {
+    Balances(balances::Event<TestConfig>),
+    ProofOfExistence(proof_of_existence::Event<TestConfig>),
+    Vesting(vesting::Event<TestConfig>),
+}
+
+impl From<balances::Event<TestConfig>> for TestEvent {
+    fn from(event: balances::Event<TestConfig>) -> Self {
+        TestEvent::Balances(event)
+    }
+}
+
+impl From<proof_of_existence::Event<TestConfig>> for TestEvent {
+    fn from(event: proof_of_existence::Event<TestConfig>) -> Self {
+        TestEvent::ProofOfExistence(event)
+    }
+}
+
+impl From<vesting::Event<TestConfig>> for TestEvent {
+    fn from(event: vesting::Event<TestConfig>) -> Self {
+        TestEvent::Vesting(event)
+    }
+}
+
+impl system::Config for TestConfig {
+    type AccountId = String;
+    type BlockNumber = u32;
+    type Nonce = u32;
+    type RuntimeEvent = TestEvent;
+    type RuntimeOrigin = system::Origin<String>;
+    const MAX_BLOCK_WEIGHT: u64 = 100;
+}
+
+impl balances::Config for TestConfig {
+    type Balance = u128;
+    const EXISTENTIAL_DEPOSIT: u128 = 5;
+}
+
+// What the runtime generates for its pallets
+impl FromStorage for balances::Pallet<TestConfig> {
+    fn from_storage(storage: &Storage) -> Self {
+        Self::new(storage)
+    }
+}
+
+// Everything deposited into the event log of the system pallet
+pub fn events(storage: &Storage) -> Vec<TestEvent> {
+    system::Pallet::<TestConfig>::new(storage)
+        .events()
+        .into_iter()
+        .map(|record| record.event)
+        .collect()
+}
@@ -0,0 +1,242 @@
+use crate::chain_spec::ChainSpec;
+use crate::support::backend::Backend;
+use crate::support::codec::{Decode, Encode};
+use crate::support::storage::{HasStorage, Storage};
+use crate::support::DispatchError;
+use crate::support::crypto::{write_hex, Hash};
+use crate::{types, Runtime};
+use std::io;
+
+// A node of the chain: the runtime, over the state kept by a backend
+// Every imported block is committed to the backend together with the changes it made to the state,
+// so a node opened again over the same backend starts at the last imported block, with the same state
+pub struct Node {
+    runtime: Runtime,
+    backend: Box<dyn Backend>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // the block can't be imported, the state is left unchanged
+    InvalidBlock(DispatchError),
+    // the backend can't be read or written, or what it holds is not a valid chain
+    Database(io::Error),
+    // the backend holds another chain than the one described by the chain spec
+    GenesisMismatch { expected: Hash, found: Hash },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidBlock(error) => write!(f, "Invalid block: {}", error),
+            Error::Database(error) => write!(f, "Database error: {}", error),
+            Error::GenesisMismatch { expected, found } => {
+                write!(f, "Database holds another chain: genesis hash is ")?;
+                write_hex(f, found)?;
+                write!(f, " instead of ")?;
+                write_hex(f, expected)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Database(error)
+    }
+}
+
+fn invalid_data(message: &'static str) -> Error {
+    Error::Database(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+impl Node {
+    // Opens the chain kept by `backend`, at its last imported block
+    // When the backend is empty, a new chain is started from the genesis state of `spec`, which is
+    // committed before the first block. Otherwise the chain kept by the backend must have the
+    // genesis hash of `spec`, so a node can't go on with another chain than the one it was given
+    pub fn open(mut backend: Box<dyn Backend>, spec: &ChainSpec) -> Result<Self, Error> {
+        let blocks = backend.blocks()?;
+        let state = backend.state()?;
+        if blocks.is_empty() && state.is_empty() {
+            let runtime = spec.build();
+            backend.commit(None, &runtime.storage().take_changes())?;
+            return Ok(Self { runtime, backend });
+        }
+
+        let runtime = Runtime::from_storage(&Storage::from_pairs(state));
+        let (expected, found) = (spec.genesis_hash(), runtime.system.genesis_hash());
+        if expected != found {
+            return Err(Error::GenesisMismatch { expected, found });
+        }
+        // the state must be the state left by the last stored block
+        if let Some(last) = blocks.last() {
+            let block = types::Block::decode_all(last).map_err(|_| invalid_data("stored block can't be decoded"))?;
+            if block.header.hash() != runtime.system.parent_hash() {
+                return Err(invalid_data("stored state doesn't match the last stored block"));
+            }
+        }
+        Ok(Self { runtime, backend })
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    // Executes the block, then commits it to the backend with the changes it made
+    // If the commit fails, the runtime is ahead of the backend and the node must be opened again
+    pub fn import_block(&mut self, block: types::Block) -> Result<(), Error> {
+        let encoded = block.encode();
+        self.runtime.execute_block(block).map_err(Error::InvalidBlock)?;
+        self.backend.commit(Some(&encoded), &self.runtime.storage().take_changes())?;
+        Ok(())
+    }
+
+    // The blocks imported so far, starting with block 1
+    pub fn blocks(&self) -> Result<Vec<types::Block>, Error> {
+        self.backend
+            .blocks()?
+            .iter()
+            .map(|block| types::Block::decode_all(block).map_err(|_| invalid_data("stored block can't be decoded")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, Node};
+    use crate::chain_spec::ChainSpec;
+    use crate::support::backend::DiskBackend;
+    use crate::support::crypto::{AccountId32, Pair};
+    use crate::support::storage::HasStorage;
+    use crate::support::UncheckedExtrinsic;
+    use crate::{balances, build_block, types, RuntimeCall};
+    use std::path::Path;
+
+    fn account(phrase: &str) -> AccountId32 {
+        Pair::from_phrase(phrase).public()
+    }
+
+    fn open(path: &Path) -> Node {
+        Node::open(Box::new(DiskBackend::open(path).unwrap()), &ChainSpec::development()).unwrap()
+    }
+
+    // The next block of the node, with a transfer of `amount` from alice to bob
+    fn transfer_block(node: &Node, amount: types::Balance) -> types::Block {
+        let runtime = node.runtime();
+        let call = RuntimeCall::balances(balances::Call::transfer { to: account("//Bob"), amount });
+        let extrinsic = UncheckedExtrinsic::new_signed(
+            &Pair::from_phrase("//Alice"),
+            runtime.system.get_nonce(&account("//Alice")),
+            call,
+            &runtime.system.genesis_hash(),
+        );
+        build_block(runtime, runtime.system.timestamp() + 6_000, vec![extrinsic])
+    }
+
+    #[test]
+    fn node_restarts_at_last_imported_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut node = open(dir.path());
+        let genesis_hash = node.runtime().system.genesis_hash();
+        for amount in [10, 20] {
+            node.import_block(transfer_block(&node, amount)).unwrap();
+        }
+        let state_root = node.runtime().storage().state_root();
+        let parent_hash = node.runtime().system.parent_hash();
+        // the node is killed
+        drop(node);
+
+        let mut node = open(dir.path());
+        assert_eq!(node.runtime().storage().state_root(), state_root);
+        assert_eq!(node.runtime().system.parent_hash(), parent_hash);
+        assert_eq!(node.runtime().system.genesis_hash(), genesis_hash);
+        assert_eq!(node.runtime().system.block_number(), 2);
+        assert_eq!(node.runtime().balances.balance(&account("//Bob")), 30);
+        assert_eq!(node.blocks().unwrap().len(), 2);
+
+        // the chain goes on from there
+        node.import_block(transfer_block(&node, 30)).unwrap();
+        drop(node);
+        let node = open(dir.path());
+        assert_eq!(node.runtime().system.block_number(), 3);
+        assert_eq!(node.runtime().balances.balance(&account("//Bob")), 60);
+        assert_eq!(node.runtime().system.get_nonce(&account("//Alice")), 3);
+    }
+
+    #[test]
+    fn nodes_from_the_same_spec_agree_on_genesis() {
+        let (dir_1, dir_2) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut node_1 = open(dir_1.path());
+        let mut node_2 = open(dir_2.path());
+        assert_eq!(node_1.runtime().system.genesis_hash(), ChainSpec::development().genesis_hash());
+        assert_eq!(node_1.runtime().system.genesis_hash(), node_2.runtime().system.genesis_hash());
+
+        // a block built by one node is imported by the other
+        let block = transfer_block(&node_1, 10);
+        node_1.import_block(block.clone()).unwrap();
+        node_2.import_block(block).unwrap();
+        assert_eq!(node_1.runtime().storage().state_root(), node_2.runtime().storage().state_root());
+    }
+
+    #[test]
+    fn chain_of_another_spec_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut node = open(dir.path());
+        node.import_block(transfer_block(&node, 10)).unwrap();
+        let genesis_hash = node.runtime().system.genesis_hash();
+        drop(node);
+
+        let mut spec = ChainSpec::development();
+        spec.genesis.balances.balances[0].1 = 1;
+        let backend = Box::new(DiskBackend::open(dir.path()).unwrap());
+        match Node::open(backend, &spec) {
+            Err(Error::GenesisMismatch { expected, found }) => {
+                assert_eq!(expected, spec.genesis_hash());
+                assert_eq!(found, genesis_hash);
+            }
+            _ => panic!("the chain of another spec is opened"),
+        }
+        // the chain is left untouched
+        let node = open(dir.path());
+        assert_eq!(node.runtime().balances.balance(&account("//Alice")), 979);
+    }
+
+    #[test]
+    fn interrupted_import_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_log = dir.path().join("state.log");
+        let mut node = open(dir.path());
+        node.import_block(transfer_block(&node, 10)).unwrap();
+        let state_root = node.runtime().storage().state_root();
+        let state_len = std::fs::metadata(&state_log).unwrap().len();
+        let block_2 = transfer_block(&node, 20);
+        node.import_block(block_2.clone()).unwrap();
+        drop(node);
+
+        // the node was killed after writing block 2, but before writing the changes it made
+        std::fs::OpenOptions::new().write(true).open(&state_log).unwrap().set_len(state_len).unwrap();
+
+        let mut node = open(dir.path());
+        assert_eq!(node.runtime().system.block_number(), 1);
+        assert_eq!(node.runtime().storage().state_root(), state_root);
+        assert_eq!(node.blocks().unwrap().len(), 1);
+        // block 2 can be imported again
+        node.import_block(block_2).unwrap();
+        assert_eq!(node.runtime().balances.balance(&account("//Bob")), 30);
+    }
+
+    #[test]
+    fn invalid_block_is_not_committed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut node = open(dir.path());
+        let mut block = transfer_block(&node, 10);
+        block.header.state_root = [0; 32];
+
+        assert!(matches!(node.import_block(block), Err(Error::InvalidBlock(_))));
+        drop(node);
+        let node = open(dir.path());
+        assert_eq!(node.runtime().system.block_number(), 0);
+        assert!(node.blocks().unwrap().is_empty());
+    }
+}
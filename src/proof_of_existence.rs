@@ -1,83 +1,782 @@
-use crate::support::DispatchResult;
-use std::collections::BTreeMap;
-use std::fmt::Debug;
+use crate::support::bounded::{BoundedVec, Get};
+use crate::support::crypto::ContentHash;
+use crate::support::currency::{Currency, ReservableCurrency};
+use crate::support::storage::{Storage, StorageMap};
+use crate::support::{ArithmeticError, DispatchResult, FromStorage, Hooks, Weight};
+use crate::system;
+use num::{CheckedAdd, CheckedMul, Zero};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::Path;
 
-pub trait Config: crate::system::Config {
-    type Content: Debug + Ord;
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {
+    // the maximum size of a content sent on chain to be claimed, in bytes
+    // larger contents, e.g. files, are hashed off chain and claimed by their hash
+    type MaxContentLength: Get<u32>;
+    // the maximum size of the description of a claim, in bytes
+    type MaxDescriptionLength: Get<u32>;
+    // the maximum number of claims expiring in the same block, which bounds the work done to remove
+    // them at the end of that block
+    type MaxExpiringPerBlock: Get<u32>;
+    // the currency the deposits of the claims are reserved from
+    type Currency: ReservableCurrency<Self::AccountId, Balance: From<u32> + CheckedMul> + FromStorage;
+    // reserved from the owner of every claim, and released when the claim is revoked, so that
+    // claims aren't free to store forever
+    const CLAIM_DEPOSIT: BalanceOf<Self>;
+    // reserved on top of `CLAIM_DEPOSIT` for every byte of the description of a claim
+    const DEPOSIT_PER_BYTE: BalanceOf<Self>;
 }
 
-#[derive(Debug)]
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+pub type DescriptionOf<T> = BoundedVec<u8, <T as Config>::MaxDescriptionLength>;
+pub type ClaimOf<T> =
+    ClaimInfo<<T as system::Config>::AccountId, <T as system::Config>::BlockNumber, BalanceOf<T>, DescriptionOf<T>>;
+pub type ExpiringOf<T> = BoundedVec<ContentHash, <T as Config>::MaxExpiringPerBlock>;
+
+// The weight of removing an expired claim at the end of a block, half of revoking it, which also
+// has a caller to check and a fee to charge
+pub const EXPIRE_CLAIM_WEIGHT: Weight = 5;
+pub type OwnershipOf<T> = Ownership<<T as system::Config>::AccountId, <T as system::Config>::BlockNumber>;
+
+// Claims are about the hash of a content, so any content can be claimed for the same cost, and
+// nothing of the content is revealed on chain
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pallet<T: Config> {
-    claims: BTreeMap<T::Content, T::AccountId>,
+    storage: Storage,
+    claims: StorageMap<ContentHash, ClaimOf<T>>,
+    // the account a claim is offered to by its owner, until it accepts it
+    offers: StorageMap<ContentHash, T::AccountId>,
+    // the previous owners of every claim which changed hands, in order
+    history: StorageMap<ContentHash, Vec<OwnershipOf<T>>>,
+    // the claims expiring in every block, removed at the end of that block
+    expiring: StorageMap<T::BlockNumber, ExpiringOf<T>>,
+}
+
+// Who claimed a content, and when
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct ClaimInfo<AccountId, BlockNumber, Balance, Description> {
+    pub owner: AccountId,
+    // what is reserved from the owner for the claim, genesis claims have no deposit
+    pub deposit: Balance,
+    // the block in which the claim was created
+    pub block_number: BlockNumber,
+    // the timestamp of that block, in milliseconds since the unix epoch
+    // genesis claims have none, they were not created in a block
+    pub timestamp: Option<u64>,
+    // what the claimed content is, as told by its owner
+    pub description: Option<Description>,
+    // the block from which the claim is expired, and at the end of which it is removed
+    // a claim without one lasts until it is revoked
+    pub expires_at: Option<BlockNumber>,
+}
+
+// A previous owner of a claim, from the block in which it got the claim to the block in which it
+// handed it over
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Ownership<AccountId, BlockNumber> {
+    pub owner: AccountId,
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+// The initial state of the proof of existence pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "T::AccountId: Serialize", deserialize = "T::AccountId: Deserialize<'de>"))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // the claims registered before the first block, with their owner
+    pub claims: Vec<(ContentHash, T::AccountId)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { claims: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // `who` claimed the ownership of `claim`
+    ClaimCreated { who: T::AccountId, claim: ContentHash },
+    // `who` revoked their claim on `claim`
+    ClaimRevoked { who: T::AccountId, claim: ContentHash },
+    // the claim on `claim` was handed over from `from` to `to`
+    ClaimTransferred { from: T::AccountId, to: T::AccountId, claim: ContentHash },
+    // `from` offered its claim on `claim` to `to`, which has to accept it
+    ClaimOffered { from: T::AccountId, to: T::AccountId, claim: ContentHash },
+    // `who` withdrew the offer of its claim on `claim`
+    OfferCancelled { who: T::AccountId, claim: ContentHash },
+    // `who` set the block from which its claim on `claim` is expired, `None` if it no longer expires
+    ClaimRenewed { who: T::AccountId, claim: ContentHash, expires_at: Option<T::BlockNumber> },
+    // the claim of `who` on `claim` expired and was removed, its deposit given back to `who`
+    ClaimExpired { who: T::AccountId, claim: ContentHash },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the content has already been claimed
+    ClaimAlreadyExists,
+    // there is no claim for the content
+    NoClaimFound,
+    // the caller does not own the claim
+    NotClaimOwner,
+    // the claim is handed over to its own owner
+    TransferToSelf,
+    // the claim isn't offered to the caller
+    NoClaimOffer,
+    // the caller can't reserve the deposit of the claim from its free balance
+    CannotAffordDeposit,
+    // the claim would be expired already, its expiry must come after the current block
+    InvalidExpiry,
+    // the most claims expiring in the same block already expire in that block
+    TooManyExpiring,
 }
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
-    pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-        match self.get_claim(&claim) {
-            Some(_) => Err("Claim already exists"),
-            None => {
-                self.claims.insert(claim, caller);
-                Ok(())
+    // Creating or revoking a claim reads and writes a claim
+    // A claim with `expires_at` is removed at the end of that block, unless it is renewed
+    #[weight(10)]
+    pub fn create_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash,
+                        description: Option<DescriptionOf<T>>,
+                        expires_at: Option<T::BlockNumber>) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let system = system::Pallet::<T>::new(&self.storage);
+        if let Some(info) = self.claims.get(&claim) {
+            match Self::is_expired(&info, system.block_number()) {
+                // an expired claim not removed yet is removed right away, the content is free again
+                true => self.expire_claim(claim, info),
+                false => return Err(Error::ClaimAlreadyExists.into()),
             }
         }
+        Self::ensure_expiry(expires_at, system.block_number())?;
+        let deposit = Self::deposit_for(&description)?;
+        self.reserve_deposit(&caller, deposit)?;
+        if let Some(at) = expires_at {
+            self.schedule_expiry(claim, at)?;
+        }
+
+        let info = ClaimInfo {
+            owner: caller.clone(),
+            deposit,
+            block_number: system.block_number(),
+            timestamp: Some(system.timestamp()),
+            description,
+            expires_at,
+        };
+        self.claims.insert(&claim, &info);
+        self.deposit_event(Event::ClaimCreated { who: caller, claim });
+        Ok(())
+    }
+
+    // A revoked claim is gone with its history, claiming the content again starts a new history
+    // Its deposit is given back to its owner
+    #[weight(10)]
+    pub fn revoke_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let info = self.ensure_owner(&claim, &caller)?;
+        self.remove_claim(claim, &info);
+        self.deposit_event(Event::ClaimRevoked { who: caller, claim });
+        Ok(())
+    }
+
+    // Claims a content sent as is, which is hashed on chain. Hashing costs one more unit of weight
+    // for every 64 bytes of content
+    #[weight(10 + content.len() as u64 / 64)]
+    pub fn create_claim_for_content(&mut self, origin: T::RuntimeOrigin,
+                                    content: BoundedVec<u8, T::MaxContentLength>,
+                                    description: Option<DescriptionOf<T>>,
+                                    expires_at: Option<T::BlockNumber>) -> DispatchResult {
+        self.create_claim(origin, ContentHash::of(&content), description, expires_at)
+    }
+
+    // Hands the claim over to `new_owner` right away
+    #[weight(10)]
+    pub fn transfer_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash,
+                          new_owner: T::AccountId) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let info = self.ensure_owner(&claim, &caller)?;
+        if new_owner == caller {
+            return Err(Error::TransferToSelf.into());
+        }
+        self.do_transfer(claim, info, new_owner)
     }
 
-    pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-        let claim_owner = self.get_claim(&claim).ok_or("No claim found for this content")?;
-        if *claim_owner != caller {
-            return Err("You are not the owner of this claim");
+    // Offers the claim to `new_owner`, which gets it once it accepts it with `accept_claim`
+    // A new offer replaces the previous one
+    #[weight(10)]
+    pub fn offer_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash,
+                       new_owner: T::AccountId) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        self.ensure_owner(&claim, &caller)?;
+        if new_owner == caller {
+            return Err(Error::TransferToSelf.into());
         }
-        self.claims.remove(&claim);
+        self.offers.insert(&claim, &new_owner);
+        self.deposit_event(Event::ClaimOffered { from: caller, to: new_owner, claim });
+        Ok(())
+    }
+
+    #[weight(10)]
+    pub fn accept_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        if self.claim_offer(&claim).as_ref() != Some(&caller) {
+            return Err(Error::NoClaimOffer.into());
+        }
+        let info = self.claim_info(&claim).ok_or(Error::NoClaimFound)?;
+        self.do_transfer(claim, info, caller)
+    }
+
+    #[weight(10)]
+    pub fn cancel_claim_offer(&mut self, origin: T::RuntimeOrigin, claim: ContentHash) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        self.ensure_owner(&claim, &caller)?;
+        if !self.offers.contains_key(&claim) {
+            return Err(Error::NoClaimOffer.into());
+        }
+        self.offers.remove(&claim);
+        self.deposit_event(Event::OfferCancelled { who: caller, claim });
+        Ok(())
+    }
+
+    // Moves the expiry of the claim to `expires_at`, or makes it last until it is revoked with `None`
+    // An expired claim can't be renewed, it is gone
+    #[weight(10)]
+    pub fn renew_claim(&mut self, origin: T::RuntimeOrigin, claim: ContentHash,
+                       expires_at: Option<T::BlockNumber>) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        let mut info = self.ensure_owner(&claim, &caller)?;
+        Self::ensure_expiry(expires_at, system::Pallet::<T>::new(&self.storage).block_number())?;
+        if let Some(at) = info.expires_at {
+            self.unschedule_expiry(claim, at);
+        }
+        if let Some(at) = expires_at {
+            self.schedule_expiry(claim, at)?;
+        }
+        info.expires_at = expires_at;
+        self.claims.insert(&claim, &info);
+        self.deposit_event(Event::ClaimRenewed { who: caller, claim, expires_at });
         Ok(())
     }
 }
 
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+    // Reserves the weight of removing the claims expiring in block `n`
+    // No claim can be set to expire in the current block, so none is added once the block started
+    fn on_initialize(&mut self, n: T::BlockNumber) -> Weight {
+        let expiring = self.expiring.get(&n).map_or(0, |expiring| expiring.len());
+        expiring as Weight * EXPIRE_CLAIM_WEIGHT
+    }
+
+    // Removes the claims expiring in block `n`, at most `MaxExpiringPerBlock` of them
+    fn on_finalize(&mut self, n: T::BlockNumber) {
+        let expiring = self.expiring.get(&n).map(BoundedVec::into_inner).unwrap_or_default();
+        for claim in expiring {
+            if let Some(info) = self.claims.get(&claim) {
+                self.expire_claim(claim, info);
+            }
+        }
+        self.expiring.remove(&n);
+    }
+}
+
 impl<T: Config> Pallet<T> {
-    pub fn new() -> Self {
+    pub fn new(storage: &Storage) -> Self {
         Self {
-            claims: BTreeMap::new(),
+            storage: storage.clone(),
+            claims: StorageMap::new(storage, "ProofOfExistence", "Claims"),
+            offers: StorageMap::new(storage, "ProofOfExistence", "Offers"),
+            history: StorageMap::new(storage, "ProofOfExistence", "History"),
+            expiring: StorageMap::new(storage, "ProofOfExistence", "Expiring"),
+        }
+    }
+
+    // Sets up the genesis state, before the first block
+    // Genesis claims don't deposit events, there is no block yet to record them
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        let block_number = system::Pallet::<T>::new(&self.storage).block_number();
+        for (claim, owner) in &config.claims {
+            let info = ClaimInfo {
+                owner: owner.clone(),
+                deposit: BalanceOf::<T>::zero(),
+                block_number,
+                timestamp: None,
+                description: None,
+                expires_at: None,
+            };
+            self.claims.insert(claim, &info);
+        }
+    }
+
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    // The owner of the claim on `content`
+    // The queries treat an expired claim as absent, even before it is removed at the end of the block
+    pub fn get_claim(&self, content: &ContentHash) -> Option<T::AccountId> {
+        self.claim_info(content).map(|info| info.owner)
+    }
+
+    // The whole record of the claim on `content`
+    pub fn claim_info(&self, content: &ContentHash) -> Option<ClaimOf<T>> {
+        let now = system::Pallet::<T>::new(&self.storage).block_number();
+        self.claims.get(content).filter(|info| !Self::is_expired(info, now))
+    }
+
+    // The previous owners of the claim on `content`, the first one being its creator
+    // Along with the current owner, they make up the chain of custody of the content
+    pub fn claim_history(&self, content: &ContentHash) -> Vec<OwnershipOf<T>> {
+        match self.claim_info(content) {
+            Some(_) => self.history.get(content).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    // The account the claim on `content` is offered to, if any
+    pub fn claim_offer(&self, content: &ContentHash) -> Option<T::AccountId> {
+        self.claim_info(content).and(self.offers.get(content))
+    }
+
+    // The claim, if `who` owns it
+    fn ensure_owner(&self, claim: &ContentHash, who: &T::AccountId) -> Result<ClaimOf<T>, Error> {
+        let info = self.claim_info(claim).ok_or(Error::NoClaimFound)?;
+        match &info.owner == who {
+            true => Ok(info),
+            false => Err(Error::NotClaimOwner),
+        }
+    }
+
+    // A claim is expired from the block it expires at
+    fn is_expired(info: &ClaimOf<T>, now: T::BlockNumber) -> bool {
+        info.expires_at.is_some_and(|at| at <= now)
+    }
+
+    fn ensure_expiry(expires_at: Option<T::BlockNumber>, now: T::BlockNumber) -> Result<(), Error> {
+        match expires_at.is_some_and(|at| at <= now) {
+            true => Err(Error::InvalidExpiry),
+            false => Ok(()),
+        }
+    }
+
+    fn schedule_expiry(&mut self, claim: ContentHash, at: T::BlockNumber) -> Result<(), Error> {
+        let mut expiring = self.expiring.get(&at).map(BoundedVec::into_inner).unwrap_or_default();
+        expiring.push(claim);
+        let expiring = ExpiringOf::<T>::try_from(expiring).map_err(|_| Error::TooManyExpiring)?;
+        self.expiring.insert(&at, &expiring);
+        Ok(())
+    }
+
+    fn unschedule_expiry(&mut self, claim: ContentHash, at: T::BlockNumber) {
+        let mut expiring = self.expiring.get(&at).map(BoundedVec::into_inner).unwrap_or_default();
+        expiring.retain(|expiring| expiring != &claim);
+        match ExpiringOf::<T>::try_from(expiring) {
+            Ok(expiring) if !expiring.is_empty() => self.expiring.insert(&at, &expiring),
+            _ => self.expiring.remove(&at),
+        }
+    }
+
+    // Removes the claim with its offer and history, and gives its deposit back to its owner
+    fn remove_claim(&mut self, claim: ContentHash, info: &ClaimOf<T>) {
+        T::Currency::from_storage(&self.storage).unreserve(&info.owner, info.deposit);
+        if let Some(at) = info.expires_at {
+            self.unschedule_expiry(claim, at);
+        }
+        self.claims.remove(&claim);
+        self.offers.remove(&claim);
+        self.history.remove(&claim);
+    }
+
+    fn expire_claim(&mut self, claim: ContentHash, info: ClaimOf<T>) {
+        self.remove_claim(claim, &info);
+        self.deposit_event(Event::ClaimExpired { who: info.owner, claim });
+    }
+
+    // Only a caller whose spendable funds are too low can't afford the deposit, any other error of
+    // the currency is returned as it is
+    fn reserve_deposit(&self, who: &T::AccountId, deposit: BalanceOf<T>) -> DispatchResult {
+        let mut currency = T::Currency::from_storage(&self.storage);
+        if !currency.can_reserve(who, deposit) {
+            return Err(Error::CannotAffordDeposit.into());
         }
+        currency.reserve(who, deposit)
     }
 
-    pub fn get_claim(&self, content: &T::Content) -> Option<&T::AccountId> {
-        self.claims.get(&content)
+    // A fixed deposit, and a deposit for every byte of the description
+    fn deposit_for(description: &Option<DescriptionOf<T>>) -> Result<BalanceOf<T>, ArithmeticError> {
+        let bytes = description.as_ref().map_or(0, |description| description.len() as u32);
+        T::DEPOSIT_PER_BYTE
+            .checked_mul(&bytes.into())
+            .and_then(|deposit| deposit.checked_add(&T::CLAIM_DEPOSIT))
+            .ok_or(ArithmeticError::Overflow)
     }
+
+    // Hands the claim over, the previous owner joins the history of the claim, which drops any offer
+    // The new owner reserves a deposit of its own, and the deposit of the previous owner is released
+    fn do_transfer(&mut self, claim: ContentHash, mut info: ClaimOf<T>, new_owner: T::AccountId) -> DispatchResult {
+        let deposit = Self::deposit_for(&info.description)?;
+        self.reserve_deposit(&new_owner, deposit)?;
+        T::Currency::from_storage(&self.storage).unreserve(&info.owner, info.deposit);
+        info.deposit = deposit;
+        let mut history = self.claim_history(&claim);
+        // the previous owner got the claim when it was created, or from the owner before it
+        let from_block = history.last().map_or(info.block_number, |ownership| ownership.to_block);
+        let to_block = system::Pallet::<T>::new(&self.storage).block_number();
+        let from = std::mem::replace(&mut info.owner, new_owner.clone());
+        history.push(Ownership { owner: from.clone(), from_block, to_block });
+        self.history.insert(&claim, &history);
+        self.claims.insert(&claim, &info);
+        self.offers.remove(&claim);
+        self.deposit_event(Event::ClaimTransferred { from, to: new_owner, claim });
+        Ok(())
+    }
+
+    // The claim on the content of the file at `path`, if it is claimed
+    pub fn get_file_claim(&self, path: impl AsRef<Path>) -> io::Result<Option<ClaimOf<T>>> {
+        Ok(self.claim_info(&hash_file(path)?))
+    }
+}
+
+// The hash a file is claimed by, read in chunks so that a file of any size can be hashed
+pub fn hash_file(path: impl AsRef<Path>) -> io::Result<ContentHash> {
+    ContentHash::of_reader(File::open(path)?)
 }
 
 #[cfg(test)]
 mod test {
-    struct TestConfig;
+    use super::{ClaimInfo, Error, Event, GenesisConfig, Ownership};
+    use crate::balances;
+    use crate::mock::{self, TestConfig, TestEvent};
+    use crate::support::bounded::{BoundedVec, ConstU32};
+    use crate::support::crypto::ContentHash;
+    use crate::support::currency::{Currency, ReservableCurrency};
+    use crate::support::storage::Storage;
+    use crate::support::{DispatchError, GetDispatchInfo, Header, Hooks};
+    use crate::system::{self, Origin};
+    use std::io::Write;
 
     impl super::Config for TestConfig {
-        type Content = &'static str;
+        type MaxContentLength = ConstU32<128>;
+        type MaxDescriptionLength = ConstU32<16>;
+        type MaxExpiringPerBlock = ConstU32<2>;
+        type Currency = balances::Pallet<TestConfig>;
+        const CLAIM_DEPOSIT: u128 = 10;
+        const DEPOSIT_PER_BYTE: u128 = 1;
+    }
+
+    // alice, bob and charlie hold 100 each, to pay for the deposits of their claims
+    fn new_pallet(storage: &Storage) -> super::Pallet<TestConfig> {
+        balances::Pallet::<TestConfig>::new(storage).build_genesis(&balances::GenesisConfig {
+            balances: vec![("alice".to_string(), 100), ("bob".to_string(), 100), ("charlie".to_string(), 100)],
+        });
+        super::Pallet::new(storage)
     }
 
-    impl crate::system::Config for TestConfig {
-        type AccountId = &'static str;
-        type BlockNumber = u32;
-        type Nonce = u32;
+    fn events(storage: &Storage) -> Vec<Event<TestConfig>> {
+        mock::events(storage)
+            .into_iter()
+            .filter_map(|event| match event {
+                TestEvent::ProofOfExistence(event) => Some(event),
+                _ => None,
+            })
+            .collect()
     }
 
     #[test]
     fn basic_proof_of_existence() {
-        let mut poe = super::Pallet::<TestConfig>::new();
+        let mut poe = new_pallet(&Storage::default());
+        let alice = || "alice".to_string();
+        let bob = || "bob".to_string();
+        let hello = || ContentHash::of(b"Hello World");
+
+        let signed = |who: String| Origin::Signed(who);
+
+        let _ = poe.create_claim(signed(alice()), hello(), None, None);
+        assert_eq!(poe.get_claim(&hello()), Some(alice()));
+        assert_eq!(poe.create_claim(signed(alice()), hello(), None, None), Err(Error::ClaimAlreadyExists.into()));
 
-        let _ = poe.create_claim("alice", "Hello World");
-        assert_eq!(poe.get_claim(&"Hello World"), Some(&"alice"));
-        assert_eq!(poe.create_claim("alice", "Hello World"), Err("Claim already exists"));
+        let res = poe.revoke_claim(signed(bob()), hello());
+        assert_eq!(res, Err(Error::NotClaimOwner.into()));
 
-        let res = poe.revoke_claim("bob", "Hello World");
-        assert_eq!(res, Err("You are not the owner of this claim"));
+        let res = poe.create_claim(signed(bob()), hello(), None, None);
+        assert_eq!(res, Err(Error::ClaimAlreadyExists.into()));
 
-        let res = poe.create_claim("bob", "Hello World");
-        assert_eq!(res, Err("Claim already exists"));
+        let res = poe.revoke_claim(signed(alice()), ContentHash::of(b"non-existent claim"));
+        assert_eq!(res, Err(Error::NoClaimFound.into()));
 
-        let res = poe.revoke_claim("alice", "non-existent claim");
-        assert_eq!(res, Err("No claim found for this content"));
+        // claims belong to accounts, not to the chain itself
+        assert_eq!(poe.create_claim(Origin::Root, ContentHash::of(b"root claim"), None, None), Err(DispatchError::BadOrigin));
+        assert_eq!(poe.revoke_claim(Origin::None, hello()), Err(DispatchError::BadOrigin));
 
-        let res = poe.revoke_claim("alice", "Hello World");
+        let res = poe.revoke_claim(signed(alice()), hello());
         assert_eq!(res, Ok(()));
-        assert_eq!(poe.get_claim(&"Hello World"), None);
+        assert_eq!(poe.get_claim(&hello()), None);
+    }
+
+    #[test]
+    fn claim_events() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        let hello = ContentHash::of(b"Hello World");
+
+        poe.create_claim(Origin::Signed("alice".to_string()), hello, None, None).unwrap();
+        let _ = poe.create_claim(Origin::Signed("bob".to_string()), hello, None, None);
+        poe.revoke_claim(Origin::Signed("alice".to_string()), hello).unwrap();
+
+        assert_eq!(events(&storage), vec![
+            Event::ClaimCreated { who: "alice".to_string(), claim: hello },
+            Event::ClaimRevoked { who: "alice".to_string(), claim: hello },
+        ]);
+    }
+
+    #[test]
+    fn content_is_claimed_by_its_hash() {
+        let mut poe = new_pallet(&Storage::default());
+        let content = BoundedVec::try_from(b"Hello World".to_vec()).unwrap();
+        assert_eq!(poe.create_claim_for_content(Origin::Signed("alice".to_string()), content.clone(), None, None), Ok(()));
+        assert_eq!(poe.get_claim(&ContentHash::of(b"Hello World")), Some("alice".to_string()));
+        assert_eq!(poe.create_claim(Origin::Signed("bob".to_string()), ContentHash::of(b"Hello World"), None, None),
+                   Err(Error::ClaimAlreadyExists.into()));
+
+        // the larger the content, the heavier the call, up to the bound of the content
+        let call = |content| super::Call::<TestConfig>::create_claim_for_content { content, description: None, expires_at: None };
+        assert_eq!(call(content).get_dispatch_info().weight, 10);
+        assert_eq!(call(BoundedVec::try_from(vec![0; 128]).unwrap()).get_dispatch_info().weight, 12);
+        assert!(BoundedVec::<u8, ConstU32<128>>::try_from(vec![0; 129]).is_err());
+    }
+
+    #[test]
+    fn files_are_claimed_by_their_hash() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"a real document").unwrap();
+        assert_eq!(super::hash_file(file.path()).unwrap(), ContentHash::of(b"a real document"));
+        assert!(super::hash_file(file.path().with_extension("missing")).is_err());
+
+        let mut poe = new_pallet(&Storage::default());
+        assert_eq!(poe.get_file_claim(file.path()).unwrap(), None);
+        poe.create_claim(Origin::Signed("alice".to_string()), super::hash_file(file.path()).unwrap(), None, None).unwrap();
+        assert_eq!(poe.get_file_claim(file.path()).unwrap().map(|info| info.owner), Some("alice".to_string()));
+    }
+
+    #[test]
+    fn claims_record_when_they_were_created() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        poe.build_genesis(&GenesisConfig { claims: vec![(ContentHash::of(b"genesis"), "alice".to_string())] });
+        let genesis = poe.claim_info(&ContentHash::of(b"genesis")).unwrap();
+        assert_eq!((genesis.block_number, genesis.timestamp, genesis.description), (0, None, None));
+
+        let hello = ContentHash::of(b"Hello World");
+        let description = || Some(BoundedVec::try_from(b"greetings".to_vec()).unwrap());
+        run_to_block(&storage, 1);
+        poe.create_claim(Origin::Signed("alice".to_string()), hello, description(), None).unwrap();
+        assert_eq!(poe.claim_info(&hello), Some(ClaimInfo {
+            owner: "alice".to_string(),
+            deposit: 19,
+            block_number: 1,
+            timestamp: Some(6_000),
+            description: description(),
+            expires_at: None,
+        }));
+
+        // a claim which is revoked and created again is a new claim, it is not dated back
+        run_to_block(&storage, 2);
+        poe.revoke_claim(Origin::Signed("alice".to_string()), hello).unwrap();
+        assert_eq!(poe.claim_info(&hello), None);
+        run_to_block(&storage, 3);
+        poe.create_claim(Origin::Signed("bob".to_string()), hello, None, None).unwrap();
+        assert_eq!(poe.claim_info(&hello), Some(ClaimInfo {
+            owner: "bob".to_string(),
+            deposit: 10,
+            block_number: 3,
+            timestamp: Some(18_000),
+            description: None,
+            expires_at: None,
+        }));
+    }
+
+    // Starts the blocks up to block `number`, each one 6 seconds after its parent, finalizing the
+    // block before
+    fn run_to_block(storage: &Storage, number: u32) {
+        let mut system = system::Pallet::<TestConfig>::new(storage);
+        while system.block_number() < number {
+            super::Pallet::<TestConfig>::new(storage).on_finalize(system.block_number());
+            let block_number = system.block_number() + 1;
+            system.initialize_block(&Header {
+                parent_hash: Default::default(),
+                block_number,
+                state_root: Default::default(),
+                extrinsics_root: Default::default(),
+                timestamp: block_number as u64 * 6_000,
+            });
+        }
+    }
+
+    #[test]
+    fn claims_are_transferred_with_their_history() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        let signed = |who: &str| Origin::Signed(who.to_string());
+        let hello = ContentHash::of(b"Hello World");
+        run_to_block(&storage, 1);
+        poe.create_claim(signed("alice"), hello, None, None).unwrap();
+
+        assert_eq!(poe.transfer_claim(signed("bob"), hello, "bob".to_string()), Err(Error::NotClaimOwner.into()));
+        assert_eq!(poe.transfer_claim(signed("alice"), hello, "alice".to_string()), Err(Error::TransferToSelf.into()));
+        assert_eq!(poe.transfer_claim(signed("alice"), ContentHash::of(b"unknown"), "bob".to_string()),
+                   Err(Error::NoClaimFound.into()));
+
+        run_to_block(&storage, 2);
+        assert_eq!(poe.transfer_claim(signed("alice"), hello, "bob".to_string()), Ok(()));
+        run_to_block(&storage, 5);
+        assert_eq!(poe.transfer_claim(signed("bob"), hello, "charlie".to_string()), Ok(()));
+
+        // the claim keeps its creation record, and the history tells who held it and when
+        let info = poe.claim_info(&hello).unwrap();
+        assert_eq!((info.owner.as_str(), info.block_number), ("charlie", 1));
+        assert_eq!(poe.claim_history(&hello), vec![
+            Ownership { owner: "alice".to_string(), from_block: 1, to_block: 2 },
+            Ownership { owner: "bob".to_string(), from_block: 2, to_block: 5 },
+        ]);
+        assert_eq!(poe.revoke_claim(signed("alice"), hello), Err(Error::NotClaimOwner.into()));
+        assert_eq!(events(&storage).pop(), Some(Event::ClaimTransferred { from: "bob".to_string(), to: "charlie".to_string(), claim: hello }));
+
+        // a revoked claim is gone with its history
+        assert_eq!(poe.revoke_claim(signed("charlie"), hello), Ok(()));
+        assert_eq!(poe.claim_history(&hello), vec![]);
+    }
+
+    #[test]
+    fn claims_hold_a_deposit() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        let balances = balances::Pallet::<TestConfig>::new(&storage);
+        let signed = |who: &str| Origin::Signed(who.to_string());
+        let alice = "alice".to_string();
+        let hello = ContentHash::of(b"Hello World");
+        let greetings = BoundedVec::try_from(b"greetings".to_vec()).unwrap();
+
+        // a fixed deposit, and one for every byte of the description
+        poe.create_claim(signed("alice"), hello, Some(greetings), None).unwrap();
+        assert_eq!(poe.claim_info(&hello).unwrap().deposit, 19);
+        assert_eq!((balances.free_balance(&alice), balances.reserved_balance(&alice)), (81, 19));
+
+        // an account that can't afford the deposit can't claim
+        assert_eq!(poe.create_claim(signed("dave"), ContentHash::of(b"dave's document"), None, None),
+                   Err(Error::CannotAffordDeposit.into()));
+        assert_eq!(poe.get_claim(&ContentHash::of(b"dave's document")), None);
+
+        // the new owner of the claim reserves its own deposit, nor can it take a claim it can't afford
+        assert_eq!(poe.transfer_claim(signed("alice"), hello, "dave".to_string()), Err(Error::CannotAffordDeposit.into()));
+        poe.transfer_claim(signed("alice"), hello, "bob".to_string()).unwrap();
+        assert_eq!((balances.free_balance(&alice), balances.reserved_balance(&alice)), (100, 0));
+        assert_eq!(balances.reserved_balance(&"bob".to_string()), 19);
+        // the deposit is released when the claim is revoked
+        poe.revoke_claim(signed("bob"), hello).unwrap();
+        assert_eq!((balances.free_balance(&"bob".to_string()), balances.reserved_balance(&"bob".to_string())), (100, 0));
+
+        // claims given at genesis hold no deposit
+        poe.build_genesis(&GenesisConfig { claims: vec![(hello, "charlie".to_string())] });
+        assert_eq!(poe.claim_info(&hello).unwrap().deposit, 0);
+        assert_eq!(poe.revoke_claim(signed("charlie"), hello), Ok(()));
+        assert_eq!(balances.free_balance(&"charlie".to_string()), 100);
+    }
+
+    #[test]
+    fn claims_expire_unless_renewed() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        let balances = balances::Pallet::<TestConfig>::new(&storage);
+        let signed = |who: &str| Origin::Signed(who.to_string());
+        let hello = ContentHash::of(b"Hello World");
+        let bye = ContentHash::of(b"Goodbye");
+        let charlie_doc = ContentHash::of(b"charlie's document");
+        run_to_block(&storage, 1);
+
+        // a claim can't be expired already, and at most two claims expire in the same block
+        assert_eq!(poe.create_claim(signed("alice"), hello, None, Some(1)), Err(Error::InvalidExpiry.into()));
+        poe.create_claim(signed("alice"), hello, None, Some(3)).unwrap();
+        poe.create_claim(signed("bob"), bye, None, Some(3)).unwrap();
+        assert_eq!(poe.create_claim(signed("charlie"), charlie_doc, None, Some(3)), Err(Error::TooManyExpiring.into()));
+        poe.create_claim(signed("charlie"), charlie_doc, None, Some(4)).unwrap();
+
+        // alice gives her claim one more block, charlie makes his last until it is revoked
+        assert_eq!(poe.renew_claim(signed("bob"), hello, Some(4)), Err(Error::NotClaimOwner.into()));
+        assert_eq!(poe.renew_claim(signed("alice"), hello, Some(1)), Err(Error::InvalidExpiry.into()));
+        assert_eq!(poe.renew_claim(signed("alice"), hello, Some(4)), Ok(()));
+        assert_eq!(poe.renew_claim(signed("charlie"), charlie_doc, None), Ok(()));
+        assert_eq!(events(&storage).pop(), Some(Event::ClaimRenewed {
+            who: "charlie".to_string(),
+            claim: charlie_doc,
+            expires_at: None,
+        }));
+
+        // bob's claim is expired from block 3, and removed at the end of it with his deposit given back
+        // the weight of removing it is reserved when block 3 starts
+        assert_eq!(poe.on_initialize(3), super::EXPIRE_CLAIM_WEIGHT);
+        assert_eq!(poe.on_initialize(5), 0);
+        run_to_block(&storage, 2);
+        assert_eq!(poe.get_claim(&bye), Some("bob".to_string()));
+        run_to_block(&storage, 3);
+        assert_eq!(poe.get_claim(&bye), None);
+        assert_eq!(balances.reserved_balance(&"bob".to_string()), 10);
+        poe.on_finalize(3);
+        assert_eq!(balances.reserved_balance(&"bob".to_string()), 0);
+        assert_eq!(events(&storage).pop(), Some(Event::ClaimExpired { who: "bob".to_string(), claim: bye }));
+        run_to_block(&storage, 4);
+
+        // alice's claim is expired in block 4, before it is removed at the end of the block
+        assert_eq!(poe.get_claim(&hello), None);
+        assert_eq!(poe.claim_info(&hello), None);
+        assert_eq!(poe.renew_claim(signed("alice"), hello, Some(10)), Err(Error::NoClaimFound.into()));
+        assert_eq!(balances.reserved_balance(&"alice".to_string()), 10);
+        // so the content can be claimed again right away
+        poe.create_claim(signed("bob"), hello, None, None).unwrap();
+        assert_eq!(balances.reserved_balance(&"alice".to_string()), 0);
+        run_to_block(&storage, 5);
+        assert_eq!(poe.get_claim(&hello), Some("bob".to_string()));
+        assert_eq!(poe.get_claim(&charlie_doc), Some("charlie".to_string()));
+    }
+
+    #[test]
+    fn offered_claims_are_transferred_once_accepted() {
+        let storage = Storage::default();
+        let mut poe = new_pallet(&storage);
+        let signed = |who: &str| Origin::Signed(who.to_string());
+        let hello = ContentHash::of(b"Hello World");
+        poe.create_claim(signed("alice"), hello, None, None).unwrap();
+
+        assert_eq!(poe.offer_claim(signed("bob"), hello, "bob".to_string()), Err(Error::NotClaimOwner.into()));
+        assert_eq!(poe.offer_claim(signed("alice"), hello, "bob".to_string()), Ok(()));
+        assert_eq!(poe.claim_offer(&hello), Some("bob".to_string()));
+        // the claim stays with its owner until the offer is accepted, by the account it is made to
+        assert_eq!(poe.get_claim(&hello), Some("alice".to_string()));
+        assert_eq!(poe.accept_claim(signed("charlie"), hello), Err(Error::NoClaimOffer.into()));
+
+        // a new offer replaces the previous one
+        assert_eq!(poe.offer_claim(signed("alice"), hello, "charlie".to_string()), Ok(()));
+        assert_eq!(poe.accept_claim(signed("bob"), hello), Err(Error::NoClaimOffer.into()));
+        assert_eq!(poe.accept_claim(signed("charlie"), hello), Ok(()));
+        assert_eq!(poe.get_claim(&hello), Some("charlie".to_string()));
+        assert_eq!(poe.claim_offer(&hello), None);
+        assert_eq!(poe.claim_history(&hello).len(), 1);
+
+        // an offer can be withdrawn, and is dropped by a direct transfer
+        poe.offer_claim(signed("charlie"), hello, "alice".to_string()).unwrap();
+        assert_eq!(poe.cancel_claim_offer(signed("alice"), hello), Err(Error::NotClaimOwner.into()));
+        assert_eq!(poe.cancel_claim_offer(signed("charlie"), hello), Ok(()));
+        assert_eq!(poe.cancel_claim_offer(signed("charlie"), hello), Err(Error::NoClaimOffer.into()));
+        assert_eq!(poe.accept_claim(signed("alice"), hello), Err(Error::NoClaimOffer.into()));
+        poe.offer_claim(signed("charlie"), hello, "alice".to_string()).unwrap();
+        poe.transfer_claim(signed("charlie"), hello, "bob".to_string()).unwrap();
+        assert_eq!(poe.accept_claim(signed("alice"), hello), Err(Error::NoClaimOffer.into()));
+
+        let events = events(&storage);
+        assert!(events.contains(&Event::ClaimOffered { from: "alice".to_string(), to: "bob".to_string(), claim: hello }));
+        assert!(events.contains(&Event::OfferCancelled { who: "charlie".to_string(), claim: hello }));
     }
 }
@@ -0,0 +1,245 @@
+use crate::support::codec::{Decode, Encode};
+use crate::support::storage::{Storage, StorageValue};
+use crate::support::{
+    with_transaction, DispatchResult, DispatchResultWithPostInfo, Dispatchable, GetDispatchInfo, Hooks, PostDispatchInfo,
+    Weight,
+};
+use crate::system;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {
+    // the aggregated call type of the runtime, generated by `#[macros::runtime]`
+    type RuntimeCall: Dispatchable<Origin = Self::RuntimeOrigin> + GetDispatchInfo + Debug + Clone + Eq + Encode + Decode;
+}
+
+// The weight of the sudo call itself, on top of the weight of the call it dispatches
+pub const SUDO_WEIGHT: Weight = 5;
+
+// This is Sudo Pallet
+// A single account, the sudo key, can dispatch any call as Root, e.g. to administer a test network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pallet<T: Config> {
+    storage: Storage,
+    // the account allowed to dispatch calls as Root, if any
+    key: StorageValue<T::AccountId>,
+}
+
+// The initial state of the sudo pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "T::AccountId: Serialize", deserialize = "T::AccountId: Deserialize<'de>"))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // the sudo key of the chain, without it nothing can be done as Root
+    pub key: Option<T::AccountId>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { key: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // a call was dispatched as Root by the sudo key, with the given result
+    Sudid { sudo_result: DispatchResult },
+    // the sudo key was given to `new`
+    KeyChanged { old: Option<T::AccountId>, new: T::AccountId },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the caller is not the sudo key
+    RequireSudo,
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    // Dispatches `call` as Root. The sudo call succeeds even if `call` fails, the result of
+    // `call` is in the `Sudid` event. It is charged for the weight of `call` on top of its own
+    // Calls decoded from an extrinsic can't nest deeper than `codec::MAX_DECODE_DEPTH`, which bounds
+    // the recursion of the weight and the dispatch of `call`
+    #[weight(call.get_dispatch_info().weight + SUDO_WEIGHT)]
+    pub fn sudo(&mut self, origin: T::RuntimeOrigin, call: Box<T::RuntimeCall>) -> DispatchResultWithPostInfo {
+        self.ensure_sudo(origin)?;
+        let info = call.get_dispatch_info();
+        // the changes of a failed call are reverted, but not the sudo call itself
+        let mut storage = self.storage.clone();
+        let result = with_transaction(&mut storage, |storage| call.dispatch(storage, system::Origin::Root.into()));
+        let actual_weight = match &result {
+            Ok(post_info) => post_info.calc_actual_weight(&info),
+            Err(_) => info.weight,
+        };
+        self.deposit_event(Event::Sudid { sudo_result: result.map(|_| ()) });
+        Ok(PostDispatchInfo { actual_weight: Some(actual_weight + SUDO_WEIGHT) })
+    }
+
+    // Gives the sudo key to another account
+    #[weight(5)]
+    pub fn set_key(&mut self, origin: T::RuntimeOrigin, new: T::AccountId) -> DispatchResult {
+        self.ensure_sudo(origin)?;
+        let old = self.key();
+        self.key.put(&new);
+        self.deposit_event(Event::KeyChanged { old, new });
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            storage: storage.clone(),
+            key: StorageValue::new(storage, "Sudo", "Key"),
+        }
+    }
+
+    // Sets up the genesis state, before the first block
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        if let Some(key) = &config.key {
+            self.key.put(key);
+        }
+    }
+
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    pub fn key(&self) -> Option<T::AccountId> {
+        self.key.get()
+    }
+
+    // Checks that the origin is signed by the sudo key
+    fn ensure_sudo(&self, origin: T::RuntimeOrigin) -> DispatchResult {
+        let caller = system::ensure_signed(origin)?;
+        match self.key() {
+            Some(key) if key == caller => Ok(()),
+            _ => Err(Error::RequireSudo.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, Event, GenesisConfig};
+    use crate::support::storage::{Storage, StorageValue};
+    use crate::support::{ArithmeticError, DispatchError, DispatchInfo, DispatchResultWithPostInfo, Dispatchable, GetDispatchInfo};
+    use crate::system::{self, Origin};
+
+    #[derive(Debug, PartialEq)]
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = Event<TestConfig>;
+        type RuntimeOrigin = system::Origin<String>;
+        const MAX_BLOCK_WEIGHT: u64 = 100;
+    }
+
+    impl super::Config for TestConfig {
+        type RuntimeCall = MockCall;
+    }
+
+    // Stands for the calls of the runtime: stores a value, which must be dispatched as Root
+    // `Fail` stores its value too, then fails
+    #[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+    enum MockCall {
+        Put(u32),
+        Fail(u32),
+    }
+
+    impl Dispatchable for MockCall {
+        type Origin = system::Origin<String>;
+
+        fn dispatch(self, storage: &Storage, origin: Self::Origin) -> DispatchResultWithPostInfo {
+            system::ensure_root(origin)?;
+            match self {
+                MockCall::Put(value) => {
+                    StorageValue::new(storage, "Mock", "Value").put(&value);
+                    Ok(().into())
+                }
+                MockCall::Fail(value) => {
+                    StorageValue::new(storage, "Mock", "Value").put(&value);
+                    Err(ArithmeticError::Overflow.into())
+                }
+            }
+        }
+    }
+
+    impl GetDispatchInfo for MockCall {
+        fn get_dispatch_info(&self) -> DispatchInfo {
+            DispatchInfo { weight: 10 }
+        }
+    }
+
+    fn value(storage: &Storage) -> Option<u32> {
+        StorageValue::new(storage, "Mock", "Value").get()
+    }
+
+    fn events(storage: &Storage) -> Vec<Event<TestConfig>> {
+        system::Pallet::<TestConfig>::new(storage).events().into_iter().map(|record| record.event).collect()
+    }
+
+    fn new_pallet(storage: &Storage) -> super::Pallet<TestConfig> {
+        let mut pallet = super::Pallet::new(storage);
+        pallet.build_genesis(&GenesisConfig { key: Some("Alice".to_string()) });
+        pallet
+    }
+
+    #[test]
+    fn sudo_dispatches_as_root() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        let call = Box::new(MockCall::Put(7));
+        assert_eq!(super::Call::<TestConfig>::sudo { call: call.clone() }.get_dispatch_info().weight, 15);
+
+        let post_info = pallet.sudo(Origin::Signed("Alice".to_string()), call).unwrap();
+        assert_eq!(post_info.actual_weight, Some(15));
+        assert_eq!(value(&storage), Some(7));
+        assert_eq!(events(&storage), vec![Event::Sudid { sudo_result: Ok(()) }]);
+    }
+
+    #[test]
+    fn only_the_key_can_sudo() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        let call = || Box::new(MockCall::Put(7));
+        assert_eq!(pallet.sudo(Origin::Signed("Bob".to_string()), call()), Err(Error::RequireSudo.into()));
+        assert_eq!(pallet.sudo(Origin::Root, call()), Err(DispatchError::BadOrigin));
+        assert_eq!(pallet.set_key(Origin::Signed("Bob".to_string()), "Bob".to_string()), Err(Error::RequireSudo.into()));
+        assert_eq!(value(&storage), None);
+
+        // without a key, nobody can sudo
+        let mut pallet = super::Pallet::<TestConfig>::new(&Storage::default());
+        assert_eq!(pallet.sudo(Origin::Signed("Alice".to_string()), call()), Err(Error::RequireSudo.into()));
+    }
+
+    #[test]
+    fn failed_call_is_reverted() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        // the sudo call itself succeeds, with the error of the inner call in its event
+        let post_info = pallet.sudo(Origin::Signed("Alice".to_string()), Box::new(MockCall::Fail(7))).unwrap();
+        assert_eq!(post_info.actual_weight, Some(15));
+        assert_eq!(value(&storage), None);
+        assert_eq!(events(&storage), vec![Event::Sudid { sudo_result: Err(ArithmeticError::Overflow.into()) }]);
+    }
+
+    #[test]
+    fn key_can_be_transferred() {
+        let storage = Storage::default();
+        let mut pallet = new_pallet(&storage);
+        assert_eq!(pallet.set_key(Origin::Signed("Alice".to_string()), "Bob".to_string()), Ok(()));
+        assert_eq!(pallet.key(), Some("Bob".to_string()));
+        assert_eq!(events(&storage), vec![Event::KeyChanged { old: Some("Alice".to_string()), new: "Bob".to_string() }]);
+
+        assert_eq!(pallet.sudo(Origin::Signed("Alice".to_string()), Box::new(MockCall::Put(7))), Err(Error::RequireSudo.into()));
+        assert!(pallet.sudo(Origin::Signed("Bob".to_string()), Box::new(MockCall::Put(7))).is_ok());
+    }
+}
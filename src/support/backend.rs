@@ -0,0 +1,277 @@
+use crate::support::codec::{Decode, Encode};
+use crate::support::crypto::blake2_256;
+use crate::support::storage::Changes;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Where a node keeps its chain: the imported blocks and the state they lead to
+// Blocks are stored encoded, in import order, so a backend doesn't depend on the runtime
+pub trait Backend {
+    // The encoded blocks imported so far, in import order
+    fn blocks(&self) -> io::Result<Vec<Vec<u8>>>;
+
+    // The state after the last imported block
+    fn state(&self) -> io::Result<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+    // Stores the changes made to the state by an imported block, or by the genesis when there is
+    // no block yet. The block and its changes are stored together or not at all
+    fn commit(&mut self, block: Option<&[u8]>, changes: &Changes) -> io::Result<()>;
+}
+
+// Applies changes on top of a state
+fn apply_changes(state: &mut BTreeMap<Vec<u8>, Vec<u8>>, changes: &Changes) {
+    for (key, value) in changes {
+        match value {
+            Some(value) => state.insert(key.clone(), value.clone()),
+            None => state.remove(key),
+        };
+    }
+}
+
+// A backend keeping everything in memory, so the chain is lost when the node stops
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    blocks: Vec<Vec<u8>>,
+    state: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Backend for MemoryBackend {
+    fn blocks(&self) -> io::Result<Vec<Vec<u8>>> {
+        Ok(self.blocks.clone())
+    }
+
+    fn state(&self) -> io::Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        Ok(self.state.clone())
+    }
+
+    fn commit(&mut self, block: Option<&[u8]>, changes: &Changes) -> io::Result<()> {
+        if let Some(block) = block {
+            self.blocks.push(block.to_vec());
+        }
+        apply_changes(&mut self.state, changes);
+        Ok(())
+    }
+}
+
+// A backend keeping the chain in a local directory, so a node can be restarted where it stopped
+// - `blocks.log` is an append-only log of the encoded blocks
+// - `state.log` is an append-only journal of the changes made to the state, the state being all
+//   the changes replayed in order
+// Every record of both files is written as `length (u32 LE) ++ payload ++ blake2_256(payload)`,
+// so a record which was only partly written when the node was killed is detected and dropped
+// A state record also holds the number of blocks in the block log once it is written, which makes
+// the state record the commit point: a block without its state record is dropped when reopening
+#[derive(Debug)]
+pub struct DiskBackend {
+    blocks_path: PathBuf,
+    state_path: PathBuf,
+    // number of blocks in the block log
+    block_count: u64,
+    // length in bytes of both logs, up to the last commit
+    blocks_len: u64,
+    state_len: u64,
+}
+
+const CHECKSUM_LEN: usize = 32;
+
+// A record of `state.log`: the number of blocks once the record is written, and the changes
+type StateRecord = (u64, Vec<(Vec<u8>, Option<Vec<u8>>)>);
+
+// Reads the complete records of a log, with the offset of the end of every record
+// Reading stops at the first record which is incomplete or doesn't match its checksum
+fn read_records(path: &Path) -> io::Result<Vec<(Vec<u8>, u64)>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while let Some(length) = data.get(offset..offset + 4) {
+        let length = u32::from_le_bytes(length.try_into().expect("slice of 4 bytes")) as usize;
+        let start = offset + 4;
+        let end = start + length + CHECKSUM_LEN;
+        let Some(record) = data.get(start..end) else {
+            break;
+        };
+        let (payload, checksum) = record.split_at(length);
+        if blake2_256(payload) != checksum {
+            break;
+        }
+        records.push((payload.to_vec(), end as u64));
+        offset = end;
+    }
+    Ok(records)
+}
+
+// Appends a record to a log, and returns its length in bytes
+fn append_record(path: &Path, payload: &[u8]) -> io::Result<u64> {
+    let mut record = Vec::with_capacity(4 + payload.len() + CHECKSUM_LEN);
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(payload);
+    record.extend_from_slice(&blake2_256(payload));
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&record)?;
+    file.sync_data()?;
+    Ok(record.len() as u64)
+}
+
+// Cuts a log after its first `length` bytes
+fn truncate(path: &Path, length: u64) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    file.set_len(length)?;
+    file.sync_all()
+}
+
+fn decode_state_record(payload: &[u8]) -> io::Result<StateRecord> {
+    StateRecord::decode_all(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.0))
+}
+
+impl DiskBackend {
+    // Opens the chain stored in `path`, creating the directory if needed
+    // Records left behind by an interrupted commit are removed, so the chain is back at the last
+    // block which was fully committed
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(&path)?;
+        let blocks_path = path.as_ref().join("blocks.log");
+        let state_path = path.as_ref().join("state.log");
+
+        let state_records = read_records(&state_path)?;
+        let committed_blocks = match state_records.last() {
+            Some((payload, _)) => decode_state_record(payload)?.0,
+            None => 0,
+        };
+        let state_len = state_records.last().map_or(0, |(_, end)| *end);
+        truncate(&state_path, state_len)?;
+
+        let block_records = read_records(&blocks_path)?;
+        if (block_records.len() as u64) < committed_blocks {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "committed blocks are missing from the block log"));
+        }
+        let blocks_end = match committed_blocks {
+            0 => 0,
+            count => block_records[count as usize - 1].1,
+        };
+        truncate(&blocks_path, blocks_end)?;
+
+        Ok(Self { blocks_path, state_path, block_count: committed_blocks, blocks_len: blocks_end, state_len })
+    }
+}
+
+impl Backend for DiskBackend {
+    fn blocks(&self) -> io::Result<Vec<Vec<u8>>> {
+        Ok(read_records(&self.blocks_path)?.into_iter().map(|(block, _)| block).collect())
+    }
+
+    fn state(&self) -> io::Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let mut state = BTreeMap::new();
+        for (payload, _) in read_records(&self.state_path)? {
+            let (_, changes) = decode_state_record(&payload)?;
+            apply_changes(&mut state, &changes.into_iter().collect());
+        }
+        Ok(state)
+    }
+
+    fn commit(&mut self, block: Option<&[u8]>, changes: &Changes) -> io::Result<()> {
+        let (mut block_count, mut blocks_len, mut state_len) = (self.block_count, self.blocks_len, self.state_len);
+        let result = (|| {
+            if let Some(block) = block {
+                blocks_len += append_record(&self.blocks_path, block)?;
+                block_count += 1;
+            }
+            let record: StateRecord = (block_count, changes.clone().into_iter().collect());
+            state_len += append_record(&self.state_path, &record.encode())?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {
+                (self.block_count, self.blocks_len, self.state_len) = (block_count, blocks_len, state_len);
+                Ok(())
+            }
+            Err(e) => {
+                // nothing was committed, whatever was written must not stay in the logs
+                truncate(&self.blocks_path, self.blocks_len)?;
+                truncate(&self.state_path, self.state_len)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Backend, DiskBackend, MemoryBackend};
+    use crate::support::storage::Changes;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    fn changes(pairs: &[(&[u8], Option<&[u8]>)]) -> Changes {
+        pairs.iter().map(|(key, value)| (key.to_vec(), value.map(|value| value.to_vec()))).collect()
+    }
+
+    fn fill(backend: &mut impl Backend) {
+        backend.commit(None, &changes(&[(b"a", Some(b"genesis"))])).unwrap();
+        backend.commit(Some(b"block 1"), &changes(&[(b"a", Some(b"1")), (b"b", Some(b"1"))])).unwrap();
+        backend.commit(Some(b"block 2"), &changes(&[(b"a", None)])).unwrap();
+    }
+
+    #[test]
+    fn memory_backend() {
+        let mut backend = MemoryBackend::default();
+        fill(&mut backend);
+        assert_eq!(backend.blocks().unwrap(), vec![b"block 1".to_vec(), b"block 2".to_vec()]);
+        assert_eq!(backend.state().unwrap().into_iter().collect::<Vec<_>>(), vec![(b"b".to_vec(), b"1".to_vec())]);
+    }
+
+    #[test]
+    fn disk_backend_is_reopened_with_the_same_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = DiskBackend::open(dir.path()).unwrap();
+        fill(&mut backend);
+        let (blocks, state) = (backend.blocks().unwrap(), backend.state().unwrap());
+        drop(backend);
+
+        let backend = DiskBackend::open(dir.path()).unwrap();
+        assert_eq!(backend.blocks().unwrap(), blocks);
+        assert_eq!(backend.state().unwrap(), state);
+    }
+
+    #[test]
+    fn partly_written_records_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = DiskBackend::open(dir.path()).unwrap();
+        fill(&mut backend);
+        let state = backend.state().unwrap();
+        drop(backend);
+
+        // the node is killed while writing the next block: its record is cut in the middle
+        let mut log = OpenOptions::new().append(true).open(dir.path().join("blocks.log")).unwrap();
+        log.write_all(&[9, 0, 0, 0, b'b', b'l']).unwrap();
+
+        let mut backend = DiskBackend::open(dir.path()).unwrap();
+        assert_eq!(backend.blocks().unwrap().len(), 2);
+        assert_eq!(backend.state().unwrap(), state);
+        // the chain goes on from there
+        backend.commit(Some(b"block 3"), &changes(&[(b"c", Some(b"3"))])).unwrap();
+        let backend = DiskBackend::open(dir.path()).unwrap();
+        assert_eq!(backend.blocks().unwrap().last().unwrap(), b"block 3");
+    }
+
+    #[test]
+    fn block_without_state_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = DiskBackend::open(dir.path()).unwrap();
+        fill(&mut backend);
+        let state = backend.state().unwrap();
+        drop(backend);
+
+        // the node is killed after writing a block, but before writing the changes it made
+        super::append_record(&dir.path().join("blocks.log"), b"block 3").unwrap();
+
+        let backend = DiskBackend::open(dir.path()).unwrap();
+        assert_eq!(backend.blocks().unwrap().len(), 2);
+        assert_eq!(backend.state().unwrap(), state);
+    }
+}
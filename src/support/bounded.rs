@@ -0,0 +1,122 @@
+use crate::support::codec::{decode_len, Decode, Encode, Error};
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+// A value given by a type, so that a bound can be part of a type, e.g. in a pallet `Config`:
+// `type MaxLength: Get<u32>;`, set by the runtime to `ConstU32<1024>`
+pub trait Get<T> {
+    fn get() -> T;
+}
+
+// Derives what the `Call` enums require of the types in the calls they hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstU32<const N: u32>;
+
+impl<const N: u32> Get<u32> for ConstU32<N> {
+    fn get() -> u32 {
+        N
+    }
+}
+
+// A `Vec` holding at most `S::get()` items
+// A longer one can't be built nor decoded, so a call taking it as an argument can't be sent more
+// than the runtime accepts: the extrinsic is rejected when it is decoded, before being dispatched
+pub struct BoundedVec<T, S>(Vec<T>, PhantomData<S>);
+
+impl<T, S: Get<u32>> BoundedVec<T, S> {
+    // The maximum number of items
+    pub fn bound() -> usize {
+        S::get() as usize
+    }
+}
+
+impl<T, S> BoundedVec<T, S> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+// Gives the `Vec` back if it is too long
+impl<T, S: Get<u32>> TryFrom<Vec<T>> for BoundedVec<T, S> {
+    type Error = Vec<T>;
+
+    fn try_from(items: Vec<T>) -> Result<Self, Self::Error> {
+        match items.len() <= Self::bound() {
+            true => Ok(Self(items, PhantomData)),
+            false => Err(items),
+        }
+    }
+}
+
+impl<T, S> Deref for BoundedVec<T, S> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+// Implemented by hand, so that the bound doesn't need to implement these traits too
+impl<T: Debug, S> Debug for BoundedVec<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Clone, S> Clone for BoundedVec<T, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T: PartialEq, S> PartialEq for BoundedVec<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq, S> Eq for BoundedVec<T, S> {}
+
+// Encoded like a `Vec`
+impl<T: Encode, S> Encode for BoundedVec<T, S> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+    }
+}
+
+impl<T: Decode, S: Get<u32>> Decode for BoundedVec<T, S> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        // the length is checked before decoding any item, a long input isn't even read
+        let len = decode_len(input)?;
+        if len > Self::bound() {
+            return Err(Error("too many items"));
+        }
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(Self(items, PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BoundedVec, ConstU32};
+    use crate::support::codec::{Decode, Encode, Error};
+
+    type Bounded = BoundedVec<u8, ConstU32<3>>;
+
+    #[test]
+    fn length_is_bounded() {
+        let bounded = Bounded::try_from(vec![1, 2, 3]).unwrap();
+        assert_eq!(&bounded[..], &[1, 2, 3]);
+        assert_eq!(Bounded::try_from(vec![1, 2, 3, 4]), Err(vec![1, 2, 3, 4]));
+        assert_eq!(bounded.clone().into_inner(), vec![1, 2, 3]);
+
+        // encoded like a `Vec`, and only decoded within the bound
+        assert_eq!(bounded.encode(), vec![1u8, 2, 3].encode());
+        assert_eq!(Bounded::decode_all(&bounded.encode()), Ok(bounded));
+        assert_eq!(Bounded::decode_all(&vec![1u8, 2, 3, 4].encode()), Err(Error("too many items")));
+    }
+}
@@ -0,0 +1,484 @@
+// Binary encoding of the runtime types, inspired by SCALE
+// The encoding is deterministic: the same value is always encoded into the same bytes and
+// every value has a single valid encoding, so encoded values can be signed, hashed and compared
+// - integers are encoded as fixed width little endian
+// - integers can also be encoded in the compact form, see `HasCompact`
+// - sequences are prefixed with their length in the compact form
+// - enums are prefixed with the index of the variant as u8
+// - structs are the concatenation of their fields
+// Structs and enums can implement `Encode` and `Decode` with `#[derive(macros::Encode, macros::Decode)]`
+
+use std::cell::Cell;
+
+// The reason why some bytes can't be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error(pub &'static str);
+
+pub trait Encode {
+    // Appends the encoding of `self` to `dest`
+    fn encode_to(&self, dest: &mut Vec<u8>);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        self.encode_to(&mut dest);
+        dest
+    }
+}
+
+pub trait Decode: Sized {
+    // Decodes a value from the start of `input`, and advances `input` past the decoded bytes
+    fn decode(input: &mut &[u8]) -> Result<Self, Error>;
+
+    // Decodes a value which must span the whole `input`
+    fn decode_all(mut input: &[u8]) -> Result<Self, Error> {
+        let value = Self::decode(&mut input)?;
+        if !input.is_empty() {
+            return Err(Error("input has trailing bytes"));
+        }
+        Ok(value)
+    }
+}
+
+// Takes the next `len` bytes of `input`
+pub fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+    if input.len() < len {
+        return Err(Error("unexpected end of input"));
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_byte(input: &mut &[u8]) -> Result<u8, Error> {
+    Ok(read_bytes(input, 1)?[0])
+}
+
+macro_rules! impl_codec_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode_to(&self, dest: &mut Vec<u8>) {
+                    dest.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $t {
+                fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+                    let bytes = read_bytes(input, std::mem::size_of::<$t>())?;
+                    Ok(<$t>::from_le_bytes(bytes.try_into().expect("length is checked; qed")))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128);
+
+// Integers which can be encoded in the compact form
+// Small values take less space than their fixed width encoding:
+// - 0..=63: one byte, `value << 2`
+// - 64..=2^14-1: two bytes, `value << 2 | 0b01`
+// - 2^14..=2^30-1: four bytes, `value << 2 | 0b10`
+// - bigger values: one byte `(n - 4) << 2 | 0b11` followed by the `n` significant bytes of the value
+pub trait HasCompact: Sized {
+    fn encode_compact_to(&self, dest: &mut Vec<u8>);
+
+    fn decode_compact(input: &mut &[u8]) -> Result<Self, Error>;
+}
+
+fn encode_compact_u128(value: u128, dest: &mut Vec<u8>) {
+    match value {
+        0..=0x3f => dest.push((value as u8) << 2),
+        0x40..=0x3fff => dest.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes()),
+        0x4000..=0x3fff_ffff => dest.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes()),
+        _ => {
+            let len = 16 - (value.leading_zeros() / 8) as usize;
+            dest.push((((len - 4) as u8) << 2) | 0b11);
+            dest.extend_from_slice(&value.to_le_bytes()[..len]);
+        }
+    }
+}
+
+// Only the shortest encoding of a value is accepted, so every value has a single encoding
+fn decode_compact_u128(input: &mut &[u8]) -> Result<u128, Error> {
+    let first = read_byte(input)?;
+    let value = match first & 0b11 {
+        0b00 => (first >> 2) as u128,
+        0b01 => {
+            let value = (u16::from_le_bytes([first, read_byte(input)?]) >> 2) as u128;
+            if value < 0x40 {
+                return Err(Error("non canonical compact encoding"));
+            }
+            value
+        }
+        0b10 => {
+            let rest = read_bytes(input, 3)?;
+            let value = (u32::from_le_bytes([first, rest[0], rest[1], rest[2]]) >> 2) as u128;
+            if value < 0x4000 {
+                return Err(Error("non canonical compact encoding"));
+            }
+            value
+        }
+        _ => {
+            let len = (first >> 2) as usize + 4;
+            if len > 16 {
+                return Err(Error("compact value is too big"));
+            }
+            let bytes = read_bytes(input, len)?;
+            if bytes[len - 1] == 0 {
+                return Err(Error("non canonical compact encoding"));
+            }
+            let mut buf = [0u8; 16];
+            buf[..len].copy_from_slice(bytes);
+            let value = u128::from_le_bytes(buf);
+            if value <= 0x3fff_ffff {
+                return Err(Error("non canonical compact encoding"));
+            }
+            value
+        }
+    };
+    Ok(value)
+}
+
+macro_rules! impl_compact_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl HasCompact for $t {
+                fn encode_compact_to(&self, dest: &mut Vec<u8>) {
+                    encode_compact_u128(*self as u128, dest);
+                }
+
+                fn decode_compact(input: &mut &[u8]) -> Result<Self, Error> {
+                    <$t>::try_from(decode_compact_u128(input)?)
+                        .map_err(|_| Error("compact value out of range"))
+                }
+            }
+        )*
+    };
+}
+
+impl_compact_for_int!(u8, u16, u32, u64, u128);
+
+// Wrapper encoding an integer in the compact form
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compact<T>(pub T);
+
+impl<T: HasCompact> Encode for Compact<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_compact_to(dest);
+    }
+}
+
+impl<T: HasCompact> Decode for Compact<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        Ok(Compact(T::decode_compact(input)?))
+    }
+}
+
+fn encode_len(len: usize, dest: &mut Vec<u8>) {
+    (len as u64).encode_compact_to(dest);
+}
+
+pub(crate) fn decode_len(input: &mut &[u8]) -> Result<usize, Error> {
+    usize::try_from(u64::decode_compact(input)?).map_err(|_| Error("length out of range"))
+}
+
+impl Encode for bool {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match read_byte(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error("invalid boolean")),
+        }
+    }
+}
+
+impl Encode for () {
+    fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+    fn decode(_input: &mut &[u8]) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+// Fixed size arrays are not prefixed with their length
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> Decode for [u8; N] {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        Ok(read_bytes(input, N)?.try_into().expect("length is checked; qed"))
+    }
+}
+
+impl Encode for str {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        dest.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_str().encode_to(dest);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let len = decode_len(input)?;
+        let bytes = read_bytes(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error("invalid utf8 string"))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let len = decode_len(input)?;
+        // the length is not trusted for the allocation, the input could lie about it
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            None => dest.push(0),
+            Some(value) => {
+                dest.push(1);
+                value.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match read_byte(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(Error("invalid option")),
+        }
+    }
+}
+
+// Encoded like `Option`: 0 followed by the value for `Ok`, 1 followed by the error for `Err`
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Ok(value) => {
+                dest.push(0);
+                value.encode_to(dest);
+            }
+            Err(error) => {
+                dest.push(1);
+                error.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: Decode, E: Decode> Decode for Result<T, E> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match read_byte(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(E::decode(input)?)),
+            _ => Err(Error("invalid result")),
+        }
+    }
+}
+
+// A boxed value is encoded as the value itself
+impl<T: Encode> Encode for Box<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+// The most boxes decoded inside one another, e.g. `sudo` calls each holding the next one
+// Boxes are how types nest in themselves, so without a limit a few bytes per level would be enough
+// to overflow the stack of the decoder, and of everything walking the decoded value
+pub const MAX_DECODE_DEPTH: u32 = 64;
+
+thread_local! {
+    // the number of boxes being decoded on the current thread
+    static DECODE_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let depth = DECODE_DEPTH.get();
+        if depth >= MAX_DECODE_DEPTH {
+            return Err(Error("too deeply nested"));
+        }
+        DECODE_DEPTH.set(depth + 1);
+        let value = T::decode(input);
+        DECODE_DEPTH.set(depth);
+        Ok(Box::new(value?))
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+        self.1.encode_to(dest);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Compact, Decode, Encode, Error, MAX_DECODE_DEPTH};
+
+    #[derive(Debug, PartialEq, macros::Encode, macros::Decode)]
+    struct Point {
+        x: u32,
+        #[codec(compact)]
+        y: u64,
+    }
+
+    #[derive(Debug, PartialEq, macros::Encode, macros::Decode)]
+    enum Shape {
+        Empty,
+        Dot(Point),
+        Line { from: Point, to: Point },
+    }
+
+    // A type holding itself, by hand since the derives bound the types of the fields
+    #[derive(Debug, PartialEq)]
+    enum Nested {
+        Leaf,
+        Node(Box<Nested>),
+    }
+
+    impl Decode for Nested {
+        fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+            match u8::decode(input)? {
+                0 => Ok(Nested::Leaf),
+                _ => Ok(Nested::Node(Box::decode(input)?)),
+            }
+        }
+    }
+
+    #[test]
+    fn encode_integers() {
+        assert_eq!(1u8.encode(), vec![1]);
+        assert_eq!(1u32.encode(), vec![1, 0, 0, 0]);
+        assert_eq!(0x0102u16.encode(), vec![2, 1]);
+        assert_eq!(u32::decode_all(&[1, 0, 0, 0]), Ok(1));
+        assert_eq!(u32::decode_all(&[1, 0, 0]), Err(Error("unexpected end of input")));
+        assert_eq!(u8::decode_all(&[1, 0]), Err(Error("input has trailing bytes")));
+    }
+
+    #[test]
+    fn encode_compact() {
+        let cases: [(u128, Vec<u8>); 7] = [
+            (0, vec![0x00]),
+            (63, vec![0xfc]),
+            (64, vec![0x01, 0x01]),
+            (16383, vec![0xfd, 0xff]),
+            (16384, vec![0x02, 0x00, 0x01, 0x00]),
+            (1 << 30, vec![0x03, 0x00, 0x00, 0x00, 0x40]),
+            (u128::MAX, [vec![0x33], vec![0xff; 16]].concat()),
+        ];
+        for (value, encoded) in cases {
+            assert_eq!(Compact(value).encode(), encoded);
+            assert_eq!(Compact::<u128>::decode_all(&encoded), Ok(Compact(value)));
+        }
+    }
+
+    #[test]
+    fn non_canonical_compact_is_rejected() {
+        // 1 encoded on two bytes instead of one
+        assert!(Compact::<u32>::decode_all(&[0x05, 0x00]).is_err());
+        // 64 encoded on four bytes instead of two
+        assert!(Compact::<u32>::decode_all(&[0x02, 0x01, 0x00, 0x00]).is_err());
+        // a value which does not fit in the target type
+        assert!(Compact::<u8>::decode_all(&[0x01, 0x04]).is_err());
+    }
+
+    #[test]
+    fn encode_sequences() {
+        assert_eq!("ab".encode(), vec![8, b'a', b'b']);
+        assert_eq!(vec![1u8, 2].encode(), vec![8, 1, 2]);
+        assert_eq!([7u8; 2].encode(), vec![7, 7]);
+        assert_eq!(Some(1u8).encode(), vec![1, 1]);
+        assert_eq!(String::decode_all(&[8, b'a', b'b']), Ok("ab".to_string()));
+        assert_eq!(Vec::<u16>::decode_all(&[8, 1, 0, 2, 0]), Ok(vec![1, 2]));
+        assert_eq!(Option::<u8>::decode_all(&[0]), Ok(None));
+        assert_eq!(Ok::<u8, u16>(3).encode(), vec![0, 3]);
+        assert_eq!(Result::<u8, u16>::decode_all(&[1, 4, 0]), Ok(Err(4)));
+        assert_eq!(Box::new(5u8).encode(), vec![5]);
+        assert_eq!(Box::<u8>::decode_all(&[5]), Ok(Box::new(5)));
+        // the length says 2 items, but only one is there
+        assert!(Vec::<u16>::decode_all(&[8, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn derive_round_trip() {
+        let point = Point { x: 1, y: 2 };
+        assert_eq!(point.encode(), vec![1, 0, 0, 0, 8]);
+
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Dot(Point { x: 1, y: 1 }),
+            Shape::Line { from: Point { x: 0, y: 0 }, to: Point { x: 5, y: 1 << 20 } },
+        ];
+        let encoded = shapes.encode();
+        assert_eq!(&encoded[..2], &[12, 0]);
+        assert_eq!(Vec::<Shape>::decode_all(&encoded), Ok(shapes));
+        assert_eq!(Shape::decode_all(&[3]), Err(Error("invalid enum variant index")));
+    }
+
+    // `depth` nodes around a leaf
+    fn nested(depth: usize) -> Vec<u8> {
+        [vec![1; depth], vec![0]].concat()
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        let depth = MAX_DECODE_DEPTH as usize;
+        assert!(Nested::decode_all(&nested(depth)).is_ok());
+        assert_eq!(Nested::decode_all(&nested(depth + 1)), Err(Error("too deeply nested")));
+        // a huge nesting is rejected before it overflows the stack
+        assert_eq!(Nested::decode_all(&nested(1_000_000)), Err(Error("too deeply nested")));
+        // and the depth is back to zero for the next value
+        assert!(Nested::decode_all(&nested(depth)).is_ok());
+    }
+}
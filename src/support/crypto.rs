@@ -0,0 +1,210 @@
+use blake2::{digest::consts::{U16, U32}, Blake2b, Digest};
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use std::fmt::{self, Debug};
+use std::io::{self, Read};
+
+// 32 bytes hash, used for block hashes and the genesis hash
+pub type Hash = [u8; 32];
+
+// Hashes `data` with blake2b, producing a 32 bytes digest
+pub fn blake2_256(data: &[u8]) -> Hash {
+    Blake2b::<U32>::digest(data).into()
+}
+
+// Hashes `data` with blake2b, producing a 16 bytes digest
+// Used for storage keys, where a shorter hash is enough to spread the keys over the trie
+pub fn blake2_128(data: &[u8]) -> [u8; 16] {
+    Blake2b::<U16>::digest(data).into()
+}
+
+// An account identifier, which is the ed25519 public key of the account
+// Only the holder of the matching secret key can sign extrinsics on behalf of the account
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, macros::Encode, macros::Decode)]
+pub struct AccountId32(pub [u8; 32]);
+
+impl Debug for AccountId32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+// Account ids are written as their hex string, e.g. in chain spec files
+impl std::str::FromStr for AccountId32 {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        bytes_from_hex(s).map(Self)
+    }
+}
+
+impl serde::Serialize for AccountId32 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", self))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AccountId32 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// The hash of a content of any size, e.g. a document whose existence is proven on chain
+// Written as its hex string, like account ids
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, macros::Encode, macros::Decode)]
+pub struct ContentHash(pub Hash);
+
+impl ContentHash {
+    pub fn of(content: &[u8]) -> Self {
+        Self(blake2_256(content))
+    }
+
+    // Hashes everything read from `reader`, without holding it in memory, e.g. a large file
+    pub fn of_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut hasher = Blake2b::<U32>::new();
+        let mut buffer = [0; 8192];
+        loop {
+            match reader.read(&mut buffer)? {
+                0 => return Ok(Self(hasher.finalize().into())),
+                read => hasher.update(&buffer[..read]),
+            }
+        }
+    }
+}
+
+impl Debug for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl std::str::FromStr for ContentHash {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        bytes_from_hex(s).map(Self)
+    }
+}
+
+impl serde::Serialize for ContentHash {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", self))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ContentHash {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Writes `bytes` in hex with the `0x` prefix, shared by everything displayed in hex
+pub(crate) fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "0x")?;
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+// Reads 32 bytes written in hex, with or without the `0x` prefix
+fn bytes_from_hex(s: &str) -> Result<[u8; 32], &'static str> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err("expected 32 bytes in hex");
+    }
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| "invalid hex digit")?;
+    }
+    Ok(bytes)
+}
+
+// An ed25519 signature
+#[derive(Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Signature(pub [u8; 64]);
+
+impl Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl AccountId32 {
+    // Checks that `signature` was produced over `message` by the secret key of this account
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        let Ok(public) = VerifyingKey::from_bytes(&self.0) else {
+            return false;
+        };
+        let signature = ed25519_dalek::Signature::from_bytes(&signature.0);
+        public.verify(message, &signature).is_ok()
+    }
+}
+
+// An ed25519 key pair, used to sign extrinsics
+pub struct Pair(SigningKey);
+
+impl Pair {
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        Self(SigningKey::from_bytes(seed))
+    }
+
+    // Derives a key pair from a phrase, the seed being the hash of the phrase
+    // Only meant for development and tests, since anyone knowing the phrase knows the secret key
+    pub fn from_phrase(phrase: &str) -> Self {
+        Self::from_seed(&blake2_256(phrase.as_bytes()))
+    }
+
+    pub fn public(&self) -> AccountId32 {
+        AccountId32(self.0.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.0.sign(message).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{blake2_256, AccountId32, ContentHash, Pair};
+
+    #[test]
+    fn sign_and_verify() {
+        let alice = Pair::from_phrase("//Alice");
+        let bob = Pair::from_phrase("//Bob");
+        let signature = alice.sign(b"hello");
+
+        assert!(alice.public().verify(b"hello", &signature));
+        assert!(!alice.public().verify(b"hello!", &signature));
+        assert!(!bob.public().verify(b"hello", &signature));
+    }
+
+    #[test]
+    fn account_id_from_hex() {
+        let alice = Pair::from_phrase("//Alice").public();
+        assert_eq!(format!("{:?}", alice).parse::<AccountId32>(), Ok(alice));
+        assert_eq!(format!("{:?}", alice)[2..].parse::<AccountId32>(), Ok(alice));
+        assert!("0x1234".parse::<AccountId32>().is_err());
+        assert!(format!("0x{}", "zz".repeat(32)).parse::<AccountId32>().is_err());
+    }
+
+    #[test]
+    fn content_hash() {
+        let hash = ContentHash::of(b"hello");
+        assert_eq!(hash.0, blake2_256(b"hello"));
+        // a content read in chunks has the same hash
+        assert_eq!(ContentHash::of_reader(&b"hello"[..]).unwrap(), hash);
+        let large = vec![7; 20_000];
+        assert_eq!(ContentHash::of_reader(&large[..]).unwrap(), ContentHash::of(&large));
+        assert_eq!(format!("{:?}", hash).parse::<ContentHash>(), Ok(hash));
+    }
+
+    #[test]
+    fn derived_keys_are_deterministic() {
+        assert_eq!(Pair::from_phrase("//Alice").public(), Pair::from_phrase("//Alice").public());
+        assert_ne!(Pair::from_phrase("//Alice").public(), Pair::from_phrase("//Bob").public());
+        assert_ne!(blake2_256(b"a"), blake2_256(b"b"));
+    }
+}
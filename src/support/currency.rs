@@ -0,0 +1,115 @@
+use crate::support::codec::{Decode, Encode};
+use crate::support::DispatchError;
+use num::{CheckedAdd, CheckedSub, Saturating, Zero};
+use std::fmt::Debug;
+
+// Traits over a pallet holding the funds of accounts, e.g. `balances::Pallet`
+// A pallet needing funds, e.g. to take a deposit, declares the currency it uses in its `Config`,
+// and the runtime chooses which pallet it is:
+//
+//   pub trait Config: system::Config {
+//       type Currency: ReservableCurrency<Self::AccountId> + FromStorage;
+//   }
+//
+//   impl my_pallet::Config for Runtime {
+//       type Currency = balances::Pallet<Runtime>;
+//   }
+//
+// The pallet then reaches the currency over its own storage, which is the storage of the runtime:
+// `T::Currency::from_storage(&self.storage)`
+
+// Whether an operation may reap the account it takes funds from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistenceRequirement {
+    // the account must keep at least the existential deposit
+    KeepAlive,
+    // the account is reaped if it is left with less than the existential deposit
+    AllowDeath,
+}
+
+// Where reserved funds moved to another account end up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum BalanceStatus {
+    Free,
+    Reserved,
+}
+
+// The name of a lock, e.g. `*b"vesting "`, so a pallet can change or remove its own lock
+pub type LockIdentifier = [u8; 8];
+
+// Funds taken out of an account and not put into another one yet
+pub trait Imbalance<Balance> {
+    fn peek(&self) -> Balance;
+
+    // Splits off `amount` of the imbalance, e.g. to route it somewhere else than the rest
+    fn split(self, amount: Balance) -> (Self, Self)
+    where
+        Self: Sized;
+}
+
+#[allow(dead_code)]
+pub trait Currency<AccountId> {
+    type Balance: CheckedAdd + CheckedSub + Saturating + Zero + PartialOrd + Copy + Debug + Encode + Decode;
+    // What is taken out of an account by `withdraw` or `slash`: dropping it burns the funds
+    type NegativeImbalance: Imbalance<Self::Balance>;
+
+    // The free and the reserved balance of `who`
+    fn total_balance(&self, who: &AccountId) -> Self::Balance;
+
+    fn free_balance(&self, who: &AccountId) -> Self::Balance;
+
+    fn total_issuance(&self) -> Self::Balance;
+
+    // The smallest balance an account can have, the existential deposit
+    fn minimum_balance(&self) -> Self::Balance;
+
+    fn transfer(&mut self, from: &AccountId, to: &AccountId, amount: Self::Balance,
+                existence: ExistenceRequirement) -> Result<(), DispatchError>;
+
+    // Takes `amount` out of the free balance of `who`
+    fn withdraw(&mut self, who: &AccountId, amount: Self::Balance,
+                existence: ExistenceRequirement) -> Result<Self::NegativeImbalance, DispatchError>;
+
+    // Takes as much as possible of `amount` out of the balance of `who`, e.g. as a punishment
+    // Returns what was taken, and what is left of `amount` which couldn't be taken
+    fn slash(&mut self, who: &AccountId, amount: Self::Balance) -> (Self::NegativeImbalance, Self::Balance);
+
+    // Puts the funds of an imbalance into the free balance of `who`
+    fn resolve_creating(&mut self, who: &AccountId, imbalance: Self::NegativeImbalance);
+}
+
+// A currency which can set funds aside in an account, e.g. as a deposit
+#[allow(dead_code)]
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+    fn reserved_balance(&self, who: &AccountId) -> Self::Balance;
+
+    // Whether `amount` of the free balance of `who` can be reserved
+    fn can_reserve(&self, who: &AccountId, amount: Self::Balance) -> bool;
+
+    // Moves `amount` of the free balance of `who` to its reserved balance
+    fn reserve(&mut self, who: &AccountId, amount: Self::Balance) -> Result<(), DispatchError>;
+
+    // Moves as much as possible of `amount` of the reserved balance of `who` back to its free
+    // balance. Returns what is left of `amount` which couldn't be unreserved
+    fn unreserve(&mut self, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+
+    // Takes as much as possible of `amount` out of the reserved balance of `who`
+    fn slash_reserved(&mut self, who: &AccountId, amount: Self::Balance) -> (Self::NegativeImbalance, Self::Balance);
+
+    // Moves as much as possible of `amount` of the reserved balance of `slashed` to the `status`
+    // balance of `beneficiary`. Returns what is left of `amount` which couldn't be moved
+    fn repatriate_reserved(&mut self, slashed: &AccountId, beneficiary: &AccountId, amount: Self::Balance,
+                           status: BalanceStatus) -> Result<Self::Balance, DispatchError>;
+}
+
+// A currency which can forbid an account to transfer some of its funds, without moving them
+#[allow(dead_code)]
+pub trait LockableCurrency<AccountId>: Currency<AccountId> {
+    // Locks `amount` of the free balance of `who` under `id`, replacing the lock with the same id
+    fn set_lock(&mut self, id: LockIdentifier, who: &AccountId, amount: Self::Balance);
+
+    // Same as `set_lock`, but the lock with the same id can only grow
+    fn extend_lock(&mut self, id: LockIdentifier, who: &AccountId, amount: Self::Balance);
+
+    fn remove_lock(&mut self, id: LockIdentifier, who: &AccountId);
+}
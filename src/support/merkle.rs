@@ -0,0 +1,168 @@
+use crate::support::codec::Encode;
+use crate::support::crypto::{blake2_256, Hash};
+
+// Binary Merkle tree over a list of leaves, used for the extrinsics root of a block
+// - a leaf is hashed as `blake2_256(0x00 ++ leaf)`
+// - an inner node is hashed as `blake2_256(0x01 ++ left ++ right)`
+// - on a level with an odd number of nodes, the last node is promoted to the next level as is
+// - the root of an empty tree is the zero hash
+// The prefixes make sure a leaf can never be mistaken for an inner node
+
+fn hash_leaf(leaf: &[u8]) -> Hash {
+    let mut data = Vec::with_capacity(leaf.len() + 1);
+    data.push(0x00);
+    data.extend_from_slice(leaf);
+    blake2_256(&data)
+}
+
+pub(super) fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut data = Vec::with_capacity(65);
+    data.push(0x01);
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    blake2_256(&data)
+}
+
+// Hashes a level of the tree into the next one
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks have one or two items"),
+        })
+        .collect()
+}
+
+pub fn merkle_root<L: AsRef<[u8]>>(leaves: &[L]) -> Hash {
+    let mut level = leaves.iter().map(|leaf| hash_leaf(leaf.as_ref())).collect::<Vec<_>>();
+    if level.is_empty() {
+        return Hash::default();
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+// The root of the tree of the encoded extrinsics of a block
+pub fn extrinsics_root<E: Encode>(extrinsics: &[E]) -> Hash {
+    merkle_root(&extrinsics.iter().map(|extrinsic| extrinsic.encode()).collect::<Vec<_>>())
+}
+
+// Proof that a leaf is included in a tree with a given root
+// Contains the hashes of the siblings on the path from the leaf to the root, from the bottom up
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct MerkleProof {
+    #[codec(compact)]
+    pub leaf_index: u32,
+    #[codec(compact)]
+    pub leaf_count: u32,
+    pub siblings: Vec<Hash>,
+}
+
+// Generates the inclusion proof of the leaf at `index`, or None if there is no such leaf
+pub fn merkle_proof<L: AsRef<[u8]>>(leaves: &[L], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut level = leaves.iter().map(|leaf| hash_leaf(leaf.as_ref())).collect::<Vec<_>>();
+    let mut position = index;
+    let mut siblings = Vec::new();
+    while level.len() > 1 {
+        // the last node of an odd level has no sibling, it is promoted as is
+        let sibling = position ^ 1;
+        if sibling < level.len() {
+            siblings.push(level[sibling]);
+        }
+        level = next_level(&level);
+        position /= 2;
+    }
+    Some(MerkleProof { leaf_index: index as u32, leaf_count: leaves.len() as u32, siblings })
+}
+
+// Checks that `leaf` is included in the tree with the given `root`
+pub fn verify_proof(root: &Hash, leaf: &[u8], proof: &MerkleProof) -> bool {
+    if proof.leaf_index >= proof.leaf_count {
+        return false;
+    }
+    let mut hash = hash_leaf(leaf);
+    let mut position = proof.leaf_index as usize;
+    let mut width = proof.leaf_count as usize;
+    let mut siblings = proof.siblings.iter();
+    while width > 1 {
+        let sibling = position ^ 1;
+        if sibling < width {
+            let Some(sibling_hash) = siblings.next() else {
+                return false;
+            };
+            hash = if position.is_multiple_of(2) {
+                hash_node(&hash, sibling_hash)
+            } else {
+                hash_node(sibling_hash, &hash)
+            };
+        }
+        position /= 2;
+        width = width.div_ceil(2);
+    }
+    siblings.next().is_none() && hash == *root
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merkle_proof, merkle_root, verify_proof};
+    use crate::support::crypto::Hash;
+
+    fn leaves(count: u8) -> Vec<Vec<u8>> {
+        (0..count).map(|i| vec![i; 3]).collect()
+    }
+
+    #[test]
+    fn root_of_small_trees() {
+        assert_eq!(merkle_root::<Vec<u8>>(&[]), Hash::default());
+        assert_ne!(merkle_root(&leaves(1)), Hash::default());
+        // the root depends on the content and on the order of the leaves
+        let mut swapped = leaves(2);
+        swapped.swap(0, 1);
+        assert_ne!(merkle_root(&leaves(2)), merkle_root(&swapped));
+        assert_ne!(merkle_root(&leaves(3)), merkle_root(&leaves(4)));
+    }
+
+    #[test]
+    fn proofs_for_every_leaf() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index).unwrap();
+                assert!(verify_proof(&root, leaf, &proof), "leaf {} of {}", index, count);
+            }
+            assert_eq!(merkle_proof(&leaves, count as usize), None);
+        }
+    }
+
+    #[test]
+    fn invalid_proofs_are_rejected() {
+        let leaves = leaves(5);
+        let root = merkle_root(&leaves);
+        let proof = merkle_proof(&leaves, 2).unwrap();
+
+        // another leaf
+        assert!(!verify_proof(&root, &leaves[3], &proof));
+        // another position
+        let mut moved = proof.clone();
+        moved.leaf_index = 3;
+        assert!(!verify_proof(&root, &leaves[2], &moved));
+        // a tampered sibling
+        let mut tampered = proof.clone();
+        tampered.siblings[0][0] ^= 1;
+        assert!(!verify_proof(&root, &leaves[2], &tampered));
+        // an extra sibling
+        let mut extended = proof.clone();
+        extended.siblings.push(Hash::default());
+        assert!(!verify_proof(&root, &leaves[2], &extended));
+        // another root
+        assert!(!verify_proof(&merkle_root(&leaves[..4]), &leaves[2], &proof));
+    }
+}
@@ -0,0 +1,404 @@
+use codec::{Decode, Encode, HasCompact};
+use crypto::{AccountId32, Hash, Pair, Signature};
+
+pub mod backend;
+pub mod bounded;
+pub mod codec;
+pub mod crypto;
+pub mod currency;
+pub mod merkle;
+pub mod storage;
+pub mod trie;
+
+//The most primitive representation of blockchain block
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Block<Header, Extrinsic> {
+    // Contains metadata about the block
+    pub header: Header,
+    // Represents the transactions to be executed in the block
+    pub extrinsics: Vec<Extrinsic>,
+}
+
+impl<Header, Extrinsic: Encode> Block<Header, Extrinsic> {
+    // The root of the Merkle tree of the extrinsics, which must match `extrinsics_root` of the header
+    pub fn extrinsics_root(&self) -> Hash {
+        merkle::extrinsics_root(&self.extrinsics)
+    }
+
+    // Proves that the extrinsic at `index` is included in the block
+    // Can be checked against the header only, with `merkle::verify_proof` over the encoded extrinsic
+    pub fn extrinsic_proof(&self, index: usize) -> Option<merkle::MerkleProof> {
+        let leaves = self.extrinsics.iter().map(|extrinsic| extrinsic.encode()).collect::<Vec<_>>();
+        merkle::merkle_proof(&leaves, index)
+    }
+}
+
+// The header of a block, which is what gets hashed to identify the block
+// Every header commits to its parent through `parent_hash`, which chains the blocks together
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Header<BlockNumber> {
+    // hash of the header of the parent block
+    pub parent_hash: Hash,
+    #[codec(compact)]
+    pub block_number: BlockNumber,
+    // root hash of the state after executing the block, see `trie`
+    pub state_root: Hash,
+    // root hash of the Merkle tree of the encoded extrinsics included in the block, see `merkle`
+    pub extrinsics_root: Hash,
+    // time at which the block was produced, in milliseconds since the unix epoch
+    #[codec(compact)]
+    pub timestamp: u64,
+}
+
+impl<BlockNumber: HasCompact> Header<BlockNumber> {
+    // The blake2 hash of the encoded header
+    pub fn hash(&self) -> Hash {
+        crypto::blake2_256(&self.encode())
+    }
+}
+
+// Shows who is calling the function and what function is being called
+// The nonce must be the next nonce of the caller, so the same extrinsic can't be applied twice
+// An unsigned extrinsic has no caller nor nonce, its call is dispatched with the `None` origin
+// This is the "checked" form of an extrinsic, see `UncheckedExtrinsic`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extrinsic<Caller, Call, Nonce> {
+    pub signed: Option<(Caller, Nonce)>,
+    pub call: Call,
+}
+
+// The signer of an extrinsic, their signature and their nonce
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct SignaturePayload<Nonce> {
+    pub signer: AccountId32,
+    pub signature: Signature,
+    #[codec(compact)]
+    pub nonce: Nonce,
+}
+
+// An extrinsic as it is included in a block: signed by the caller, but not verified yet
+// The signature is made over the signing payload (call + nonce + genesis hash), so it can't be
+// reused for another call, another nonce or on another chain
+// The caller is the account of the public key which signed the extrinsic
+// An extrinsic without signature is an inherent: it is put in the block by its author, and is
+// only accepted by calls which expect the `None` origin
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct UncheckedExtrinsic<Call, Nonce> {
+    pub signature: Option<SignaturePayload<Nonce>>,
+    pub call: Call,
+}
+
+impl<Call: Encode, Nonce: HasCompact> UncheckedExtrinsic<Call, Nonce> {
+    // Creates an extrinsic signed with `pair`, for the chain with the given genesis hash
+    pub fn new_signed(pair: &Pair, nonce: Nonce, call: Call, genesis_hash: &Hash) -> Self {
+        let signature = pair.sign(&signing_payload(&call, &nonce, genesis_hash));
+        Self { signature: Some(SignaturePayload { signer: pair.public(), signature, nonce }), call }
+    }
+
+    // Creates an unsigned extrinsic, e.g. an inherent
+    pub fn new_unsigned(call: Call) -> Self {
+        Self { signature: None, call }
+    }
+
+    // Verifies the signature and turns the extrinsic into a checked extrinsic
+    // The caller of the checked extrinsic is the account of the signer
+    pub fn check(
+        self,
+        genesis_hash: &Hash,
+    ) -> Result<Extrinsic<AccountId32, Call, Nonce>, InvalidTransaction> {
+        let Some(SignaturePayload { signer, signature, nonce }) = self.signature else {
+            return Ok(Extrinsic { signed: None, call: self.call });
+        };
+        let payload = signing_payload(&self.call, &nonce, genesis_hash);
+        if !signer.verify(&payload, &signature) {
+            return Err(InvalidTransaction::BadProof);
+        }
+        Ok(Extrinsic { signed: Some((signer, nonce)), call: self.call })
+    }
+}
+
+// The bytes signed by the caller of an extrinsic: the encoded call, nonce and genesis hash
+fn signing_payload<Call: Encode, Nonce: HasCompact>(call: &Call, nonce: &Nonce, genesis_hash: &Hash) -> Vec<u8> {
+    let mut payload = call.encode();
+    nonce.encode_compact_to(&mut payload);
+    genesis_hash.encode_to(&mut payload);
+    payload
+}
+
+// The result of the function call
+// If everything is ok, it returns Ok(())
+// If there is an error, it returns Err(DispatchError) - describes what went wrong
+pub type DispatchResult = Result<(), DispatchError>;
+
+// The weight of a call: an estimate of the resources it takes to execute it (computation, storage
+// accesses), in abstract units. The weight of the extrinsics of a block is bounded, see `system`
+pub type Weight = u64;
+
+// What is known about a call before it is dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DispatchInfo {
+    // the weight of the call in the worst case, declared with `#[weight(...)]`
+    pub weight: Weight,
+}
+
+// What is known about a call once it is dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PostDispatchInfo {
+    // the weight actually used by the call, when it is less than its declared weight
+    pub actual_weight: Option<Weight>,
+}
+
+impl PostDispatchInfo {
+    // The weight the call is charged for, which can't be more than its declared weight
+    pub fn calc_actual_weight(&self, info: &DispatchInfo) -> Weight {
+        self.actual_weight.map_or(info.weight, |weight| weight.min(info.weight))
+    }
+}
+
+// A call returning `DispatchResult` uses its whole declared weight
+impl From<()> for PostDispatchInfo {
+    fn from(_: ()) -> Self {
+        Self::default()
+    }
+}
+
+// The result of a call which can report the weight it actually used
+pub type DispatchResultWithPostInfo = Result<PostDispatchInfo, DispatchError>;
+
+// Implemented by the `Call` enums generated by `#[macros::call]` and `#[macros::runtime]`
+pub trait GetDispatchInfo {
+    fn get_dispatch_info(&self) -> DispatchInfo;
+}
+
+// The reason why a call failed
+// Can be matched on by clients, instead of comparing error messages
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchError {
+    // An error declared by a pallet with `#[macros::error]`
+    Module(ModuleError),
+    // An arithmetic operation overflowed or underflowed
+    Arithmetic(ArithmeticError),
+    // The caller is not allowed to dispatch the call
+    BadOrigin,
+    // The extrinsic is not valid, so the block containing it is invalid too
+    InvalidTransaction(InvalidTransaction),
+    // Any other error, described by a static message
+    Other(&'static str),
+}
+
+impl DispatchError {
+    // Sets the index of the pallet which returned this error, used by the runtime during dispatch
+    // Errors other than module errors are returned unchanged
+    pub fn with_module_index(self, index: u8) -> Self {
+        match self {
+            DispatchError::Module(error) => DispatchError::Module(ModuleError { index, ..error }),
+            other => other,
+        }
+    }
+}
+
+impl std::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatchError::Module(ModuleError { index, error, message }) => {
+                write!(f, "{} (pallet {}, error {})", message.unwrap_or("Module error"), index, error)
+            }
+            DispatchError::Arithmetic(error) => write!(f, "Arithmetic error: {:?}", error),
+            DispatchError::BadOrigin => write!(f, "Bad origin"),
+            DispatchError::InvalidTransaction(error) => write!(f, "Invalid transaction: {:?}", error),
+            DispatchError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// Encoded like a derived enum, except that the message of `Other` is not encoded
+// It is decoded as an empty message, since a static message can't be decoded
+impl Encode for DispatchError {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            DispatchError::Module(error) => {
+                dest.push(0);
+                error.encode_to(dest);
+            }
+            DispatchError::Arithmetic(error) => {
+                dest.push(1);
+                error.encode_to(dest);
+            }
+            DispatchError::BadOrigin => dest.push(2),
+            DispatchError::InvalidTransaction(error) => {
+                dest.push(3);
+                error.encode_to(dest);
+            }
+            DispatchError::Other(_) => dest.push(4),
+        }
+    }
+}
+
+impl Decode for DispatchError {
+    fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+        match u8::decode(input)? {
+            0 => Ok(DispatchError::Module(ModuleError::decode(input)?)),
+            1 => Ok(DispatchError::Arithmetic(ArithmeticError::decode(input)?)),
+            2 => Ok(DispatchError::BadOrigin),
+            3 => Ok(DispatchError::InvalidTransaction(InvalidTransaction::decode(input)?)),
+            4 => Ok(DispatchError::Other("")),
+            _ => Err(codec::Error("invalid enum variant index")),
+        }
+    }
+}
+
+impl From<ArithmeticError> for DispatchError {
+    fn from(error: ArithmeticError) -> Self {
+        DispatchError::Arithmetic(error)
+    }
+}
+
+impl From<InvalidTransaction> for DispatchError {
+    fn from(error: InvalidTransaction) -> Self {
+        DispatchError::InvalidTransaction(error)
+    }
+}
+
+impl From<&'static str> for DispatchError {
+    fn from(message: &'static str) -> Self {
+        DispatchError::Other(message)
+    }
+}
+
+// An error of a specific pallet
+// Identified by the index of the pallet in the runtime and the index of the error in the pallet `Error` enum
+#[derive(Debug, Clone, Copy, Eq)]
+pub struct ModuleError {
+    // index of the pallet in the runtime (system is 0)
+    pub index: u8,
+    // index of the variant in the pallet `Error` enum
+    pub error: u8,
+    // name of the error variant, only used for display
+    pub message: Option<&'static str>,
+}
+
+// The message is only informational, two module errors are equal if they have the same indices
+impl PartialEq for ModuleError {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.error == other.error
+    }
+}
+
+// Only the indices are encoded, which keeps module errors two bytes long
+impl Encode for ModuleError {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(self.index);
+        dest.push(self.error);
+    }
+}
+
+impl Decode for ModuleError {
+    fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+        Ok(ModuleError { index: u8::decode(input)?, error: u8::decode(input)?, message: None })
+    }
+}
+
+// Reasons why an extrinsic can't be included in a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum InvalidTransaction {
+    // the nonce was already used, e.g. the extrinsic is replayed
+    Stale,
+    // the nonce is ahead of the next nonce of the caller
+    Future,
+    // the signature does not match the signer and the signing payload
+    BadProof,
+    // the caller can't pay the fee of the extrinsic
+    Payment,
+    // the extrinsic doesn't fit in the weight left in the block
+    ExhaustsResources,
+    // an unsigned extrinsic whose call failed, only inherents which succeed can be included
+    BadInherent,
+}
+
+// Errors of checked arithmetic operations
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum ArithmeticError {
+    Underflow,
+    Overflow,
+    DivisionByZero,
+}
+
+// A trait that defines the dispatch function
+// Allows us to call functions on the blockchain
+// Allows us to dispatch an incoming extrinsic to the appropriate state transition function call
+pub trait Dispatch {
+    // The type used to identify where the call comes from, see `system::Origin`
+    type Origin;
+    // The state transition function call the caller trying to access
+    type Call;
+
+    // This function takes the 'origin' and the 'call' they want to make, and returns the result of the function call
+    fn dispatch(&mut self, origin: Self::Origin, call: Self::Call) -> DispatchResultWithPostInfo;
+}
+
+// A call which can be dispatched on its own, over the storage of the runtime
+// Implemented by `RuntimeCall`, so a pallet can dispatch calls of any pallet, e.g. sudo
+pub trait Dispatchable {
+    type Origin;
+
+    fn dispatch(self, storage: &storage::Storage, origin: Self::Origin) -> DispatchResultWithPostInfo;
+}
+
+// A pallet which can be created over the storage of the runtime, so another pallet can reach it
+// Implemented by `#[macros::runtime]` for every pallet of the runtime, see `support::currency`
+pub trait FromStorage {
+    fn from_storage(storage: &storage::Storage) -> Self;
+}
+
+// What a pallet does at given points of every block, nothing by default
+// Implemented by every pallet of the runtime, `#[macros::runtime]` calls the hooks of the pallets in
+// their order in the `Runtime` struct
+pub trait Hooks<BlockNumber> {
+    // Called once block `n` is started, before its extrinsics. Returns the weight of what the pallet
+    // does in the block outside of the extrinsics, e.g. in `on_finalize`, which is reserved from
+    // the block so the extrinsics can't use it
+    fn on_initialize(&mut self, _n: BlockNumber) -> Weight {
+        0
+    }
+
+    // Called once all the extrinsics of block `n` are applied, e.g. to clean up what is due in `n`
+    fn on_finalize(&mut self, _n: BlockNumber) {}
+}
+
+// How the callers of extrinsics pay for them, implemented by the runtime
+// The fee for the declared weight of the call is withdrawn before the call is dispatched, and the
+// fee for the weight it didn't use is refunded once it is dispatched, even if the call failed
+pub trait OnChargeTransaction {
+    type Caller;
+
+    // Withdraws the fee for `info` from `who`, the extrinsic is invalid if they can't pay it
+    fn withdraw_fee(&mut self, who: &Self::Caller, info: &DispatchInfo) -> Result<(), InvalidTransaction>;
+
+    // Refunds `who` the fee for the weight left unused when only `actual_weight` was used
+    fn refund_fee(&mut self, who: &Self::Caller, info: &DispatchInfo, actual_weight: Weight);
+}
+
+// Executes `f` inside a new storage transaction layer over the storage of `state`
+// - if `f` returns Ok, the changes made by `f` are kept (the layer is committed into the outer one)
+// - if `f` returns Err, the changes made by `f` are discarded (the layer is reverted)
+// Layers can be nested, so a call running inside a transaction can open its own sub-transaction
+// and only revert its own part of the work
+pub fn with_transaction<State, R, E>(
+    state: &mut State,
+    f: impl FnOnce(&mut State) -> Result<R, E>,
+) -> Result<R, E>
+where
+    State: storage::HasStorage,
+{
+    let storage = state.storage().clone();
+    storage.start_transaction();
+    let result = f(state);
+    if result.is_ok() {
+        storage.commit_transaction();
+    } else {
+        storage.rollback_transaction();
+    }
+    result
+}
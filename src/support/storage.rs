@@ -0,0 +1,537 @@
+use crate::support::codec::{Decode, Encode};
+use crate::support::crypto::{blake2_128, Hash};
+use crate::support::trie;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+// Handle to the key-value state of the chain
+// All the pallets of a runtime hold a handle to the same state, so cloning a handle doesn't copy
+// the state, it gives another view of it
+// Two handles are equal when they hold the same key-value pairs
+#[derive(Clone, Default)]
+pub struct Storage(Rc<RefCell<State>>);
+
+impl PartialEq for Storage {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.pairs() == other.pairs()
+    }
+}
+
+impl Eq for Storage {}
+
+// Changes made to key-value pairs, None being a removed key
+pub type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+#[derive(Default)]
+struct State {
+    // the committed key-value pairs
+    committed: BTreeMap<Vec<u8>, Vec<u8>>,
+    // the open transaction layers, from the outermost to the innermost
+    // every layer holds the changes made since it was opened
+    layers: Vec<Changes>,
+    // the changes committed since the last call to `take_changes`, so they can be persisted
+    changes: Changes,
+}
+
+impl State {
+    fn write_committed(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        match &value {
+            Some(value) => self.committed.insert(key.clone(), value.clone()),
+            None => self.committed.remove(&key),
+        };
+        self.changes.insert(key, value);
+    }
+}
+
+impl Storage {
+    // A storage holding the given key-value pairs, e.g. loaded from a database
+    pub fn from_pairs(pairs: BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        Self(Rc::new(RefCell::new(State { committed: pairs, ..Default::default() })))
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let state = self.0.borrow();
+        for layer in state.layers.iter().rev() {
+            if let Some(value) = layer.get(key) {
+                return value.clone();
+            }
+        }
+        state.committed.get(key).cloned()
+    }
+
+    pub fn set(&self, key: &[u8], value: Vec<u8>) {
+        self.write(key, Some(value));
+    }
+
+    pub fn remove(&self, key: &[u8]) {
+        self.write(key, None);
+    }
+
+    fn write(&self, key: &[u8], value: Option<Vec<u8>>) {
+        let mut state = self.0.borrow_mut();
+        match state.layers.last_mut() {
+            Some(layer) => {
+                layer.insert(key.to_vec(), value);
+            }
+            None => state.write_committed(key.to_vec(), value),
+        }
+    }
+
+    // All the key-value pairs, as seen from the innermost transaction layer
+    pub fn pairs(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        let state = self.0.borrow();
+        let mut pairs = state.committed.clone();
+        for layer in &state.layers {
+            for (key, value) in layer {
+                match value {
+                    Some(value) => pairs.insert(key.clone(), value.clone()),
+                    None => pairs.remove(key),
+                };
+            }
+        }
+        pairs
+    }
+
+    // The key-value pairs whose key starts with `prefix`, sorted by key
+    pub fn pairs_with_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.pairs()
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .collect()
+    }
+
+    // The root of the trie of all the key-value pairs, see `trie`
+    pub fn state_root(&self) -> Hash {
+        trie::state_root(&self.pairs())
+    }
+
+    // Proves the value of `key` against `state_root()`, see `trie::verify_read_proof`
+    pub fn read_proof(&self, key: &[u8]) -> trie::ReadProof {
+        trie::read_proof(&self.pairs(), key)
+    }
+
+    // Opens a new transaction layer, every change made until it is closed goes into that layer
+    pub fn start_transaction(&self) {
+        self.0.borrow_mut().layers.push(BTreeMap::new());
+    }
+
+    // Closes the innermost layer and applies its changes to the outer layer
+    pub fn commit_transaction(&self) {
+        let mut state = self.0.borrow_mut();
+        let layer = state.layers.pop().expect("no transaction layer to commit");
+        for (key, value) in layer {
+            match state.layers.last_mut() {
+                Some(outer) => {
+                    outer.insert(key, value);
+                }
+                None => state.write_committed(key, value),
+            }
+        }
+    }
+
+    // Closes the innermost layer and discards its changes
+    pub fn rollback_transaction(&self) {
+        self.0.borrow_mut().layers.pop().expect("no transaction layer to roll back");
+    }
+
+    // Takes the changes committed since the last call, the changes of open layers are not included
+    pub fn take_changes(&self) -> Changes {
+        std::mem::take(&mut self.0.borrow_mut().changes)
+    }
+}
+
+// The content of the state is shown by the storage items, see `StorageValue` and `StorageMap`
+impl Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Storage").field("keys", &self.pairs().len()).finish()
+    }
+}
+
+// Anything giving access to the storage, like the runtime, so it can run in a transaction
+pub trait HasStorage {
+    fn storage(&self) -> &Storage;
+}
+
+impl HasStorage for Storage {
+    fn storage(&self) -> &Storage {
+        self
+    }
+}
+
+// The prefix of all the keys of a storage item: the hashes of the pallet name and of the item name
+// Items of different pallets can have the same name without their keys colliding
+fn item_prefix(pallet: &str, item: &str) -> Vec<u8> {
+    let mut prefix = blake2_128(pallet.as_bytes()).to_vec();
+    prefix.extend_from_slice(&blake2_128(item.as_bytes()));
+    prefix
+}
+
+// The hashed form of a key of a map: the hash of the encoded key followed by the encoded key
+// The hash spreads the keys over the trie, and the key itself can still be read back when iterating
+fn hashed_key_to<K: Encode>(key: &K, dest: &mut Vec<u8>) {
+    let encoded = key.encode();
+    dest.extend_from_slice(&blake2_128(&encoded));
+    dest.extend_from_slice(&encoded);
+}
+
+// Reads back a key hashed with `hashed_key_to`
+fn decode_hashed_key<K: Decode>(input: &mut &[u8]) -> K {
+    *input = input.get(16..).expect("storage key is too short");
+    K::decode(input).expect("storage key can't be decoded")
+}
+
+fn decode_value<V: Decode>(value: &[u8]) -> V {
+    V::decode_all(value).expect("storage value can't be decoded")
+}
+
+// A single value stored under `hash(pallet) ++ hash(item)`
+pub struct StorageValue<V> {
+    storage: Storage,
+    key: Vec<u8>,
+    _value: PhantomData<V>,
+}
+
+impl<V> Clone for StorageValue<V> {
+    fn clone(&self) -> Self {
+        Self { storage: self.storage.clone(), key: self.key.clone(), _value: PhantomData }
+    }
+}
+
+impl<V> PartialEq for StorageValue<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.storage == other.storage
+    }
+}
+
+impl<V> Eq for StorageValue<V> {}
+
+impl<V: Encode + Decode> StorageValue<V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        Self { storage: storage.clone(), key: item_prefix(pallet, item), _value: PhantomData }
+    }
+
+    pub fn get(&self) -> Option<V> {
+        self.storage.get(&self.key).map(|value| decode_value(&value))
+    }
+
+    pub fn put(&self, value: &V) {
+        self.storage.set(&self.key, value.encode());
+    }
+
+    pub fn kill(&self) {
+        self.storage.remove(&self.key);
+    }
+}
+
+impl<V: Encode + Decode + Debug> Debug for StorageValue<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+// A map stored under `hash(pallet) ++ hash(item) ++ hash(key) ++ key`
+pub struct StorageMap<K, V> {
+    storage: Storage,
+    prefix: Vec<u8>,
+    _pair: PhantomData<(K, V)>,
+}
+
+impl<K, V> Clone for StorageMap<K, V> {
+    fn clone(&self) -> Self {
+        Self { storage: self.storage.clone(), prefix: self.prefix.clone(), _pair: PhantomData }
+    }
+}
+
+impl<K, V> PartialEq for StorageMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.prefix == other.prefix && self.storage == other.storage
+    }
+}
+
+impl<K, V> Eq for StorageMap<K, V> {}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        Self { storage: storage.clone(), prefix: item_prefix(pallet, item), _pair: PhantomData }
+    }
+
+    // The key of the storage under which the value of `key` is stored
+    pub fn hashed_key(&self, key: &K) -> Vec<u8> {
+        let mut hashed = self.prefix.clone();
+        hashed_key_to(key, &mut hashed);
+        hashed
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.storage.get(&self.hashed_key(key)).map(|value| decode_value(&value))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.storage.get(&self.hashed_key(key)).is_some()
+    }
+
+    pub fn insert(&self, key: &K, value: &V) {
+        self.storage.set(&self.hashed_key(key), value.encode());
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.storage.remove(&self.hashed_key(key));
+    }
+
+    // All the entries of the map, in the order of their hashed keys
+    pub fn iter(&self) -> Vec<(K, V)> {
+        self.storage
+            .pairs_with_prefix(&self.prefix)
+            .into_iter()
+            .map(|(key, value)| {
+                let key = decode_hashed_key(&mut &key[self.prefix.len()..]);
+                (key, decode_value(&value))
+            })
+            .collect()
+    }
+
+    // Proves the value of `key` against the state root, see `Storage::read_proof`
+    pub fn read_proof(&self, key: &K) -> trie::ReadProof {
+        self.storage.read_proof(&self.hashed_key(key))
+    }
+}
+
+impl<K: Encode + Decode + Debug, V: Encode + Decode + Debug> Debug for StorageMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// A map with two keys, stored under `hash(pallet) ++ hash(item) ++ hash(key1) ++ key1 ++ hash(key2) ++ key2`
+// All the entries sharing the first key are next to each other, so they can be iterated and removed together
+#[allow(dead_code)]
+pub struct StorageDoubleMap<K1, K2, V> {
+    storage: Storage,
+    prefix: Vec<u8>,
+    _pair: PhantomData<(K1, K2, V)>,
+}
+
+impl<K1, K2, V> Clone for StorageDoubleMap<K1, K2, V> {
+    fn clone(&self) -> Self {
+        Self { storage: self.storage.clone(), prefix: self.prefix.clone(), _pair: PhantomData }
+    }
+}
+
+impl<K1, K2, V> PartialEq for StorageDoubleMap<K1, K2, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.prefix == other.prefix && self.storage == other.storage
+    }
+}
+
+impl<K1, K2, V> Eq for StorageDoubleMap<K1, K2, V> {}
+
+#[allow(dead_code)]
+impl<K1: Encode + Decode, K2: Encode + Decode, V: Encode + Decode> StorageDoubleMap<K1, K2, V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        Self { storage: storage.clone(), prefix: item_prefix(pallet, item), _pair: PhantomData }
+    }
+
+    fn first_key_prefix(&self, key1: &K1) -> Vec<u8> {
+        let mut prefix = self.prefix.clone();
+        hashed_key_to(key1, &mut prefix);
+        prefix
+    }
+
+    // The key of the storage under which the value of `(key1, key2)` is stored
+    pub fn hashed_key(&self, key1: &K1, key2: &K2) -> Vec<u8> {
+        let mut hashed = self.first_key_prefix(key1);
+        hashed_key_to(key2, &mut hashed);
+        hashed
+    }
+
+    pub fn get(&self, key1: &K1, key2: &K2) -> Option<V> {
+        self.storage.get(&self.hashed_key(key1, key2)).map(|value| decode_value(&value))
+    }
+
+    pub fn contains_key(&self, key1: &K1, key2: &K2) -> bool {
+        self.storage.get(&self.hashed_key(key1, key2)).is_some()
+    }
+
+    pub fn insert(&self, key1: &K1, key2: &K2, value: &V) {
+        self.storage.set(&self.hashed_key(key1, key2), value.encode());
+    }
+
+    pub fn remove(&self, key1: &K1, key2: &K2) {
+        self.storage.remove(&self.hashed_key(key1, key2));
+    }
+
+    // All the entries under `key1`, with their second key
+    pub fn iter_prefix(&self, key1: &K1) -> Vec<(K2, V)> {
+        let prefix = self.first_key_prefix(key1);
+        self.storage
+            .pairs_with_prefix(&prefix)
+            .into_iter()
+            .map(|(key, value)| (decode_hashed_key(&mut &key[prefix.len()..]), decode_value(&value)))
+            .collect()
+    }
+
+    // Removes all the entries under `key1`
+    pub fn remove_prefix(&self, key1: &K1) {
+        for (key, _) in self.storage.pairs_with_prefix(&self.first_key_prefix(key1)) {
+            self.storage.remove(&key);
+        }
+    }
+
+    // All the entries of the map, in the order of their hashed keys
+    pub fn iter(&self) -> Vec<((K1, K2), V)> {
+        self.storage
+            .pairs_with_prefix(&self.prefix)
+            .into_iter()
+            .map(|(key, value)| {
+                let mut input = &key[self.prefix.len()..];
+                let key1 = decode_hashed_key(&mut input);
+                let key2 = decode_hashed_key(&mut input);
+                ((key1, key2), decode_value(&value))
+            })
+            .collect()
+    }
+}
+
+impl<K1, K2, V> Debug for StorageDoubleMap<K1, K2, V>
+where
+    K1: Encode + Decode + Debug,
+    K2: Encode + Decode + Debug,
+    V: Encode + Decode + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Storage, StorageDoubleMap, StorageMap, StorageValue};
+    use crate::support::trie::verify_read_proof;
+    use crate::support::codec::Encode;
+
+    #[test]
+    fn nested_transaction_layers() {
+        let storage = Storage::default();
+        storage.set(b"a", vec![1]);
+
+        storage.start_transaction();
+        storage.set(b"a", vec![2]);
+        storage.set(b"b", vec![2]);
+
+        storage.start_transaction();
+        storage.remove(b"a");
+        assert_eq!(storage.get(b"a"), None);
+        storage.rollback_transaction();
+        assert_eq!(storage.get(b"a"), Some(vec![2]));
+
+        storage.start_transaction();
+        storage.remove(b"b");
+        storage.commit_transaction();
+        assert_eq!(storage.get(b"b"), None);
+
+        storage.commit_transaction();
+        assert_eq!(storage.pairs().into_iter().collect::<Vec<_>>(), vec![(b"a".to_vec(), vec![2])]);
+    }
+
+    #[test]
+    fn committed_changes_are_tracked() {
+        let storage = Storage::default();
+        storage.set(b"a", vec![1]);
+        storage.set(b"b", vec![1]);
+        storage.take_changes();
+
+        storage.start_transaction();
+        storage.remove(b"a");
+        storage.set(b"c", vec![1]);
+        assert!(storage.take_changes().is_empty());
+        storage.commit_transaction();
+        storage.start_transaction();
+        storage.set(b"b", vec![2]);
+        storage.rollback_transaction();
+
+        let changes = storage.take_changes();
+        assert_eq!(changes.into_iter().collect::<Vec<_>>(), vec![
+            (b"a".to_vec(), None),
+            (b"c".to_vec(), Some(vec![1])),
+        ]);
+        assert_eq!(Storage::from_pairs(storage.pairs()), storage);
+    }
+
+    #[test]
+    fn rolled_back_changes_leave_the_root_unchanged() {
+        let storage = Storage::default();
+        storage.set(b"a", vec![1]);
+        let root = storage.state_root();
+
+        storage.start_transaction();
+        storage.set(b"b", vec![1]);
+        assert_ne!(storage.state_root(), root);
+        storage.rollback_transaction();
+
+        assert_eq!(storage.state_root(), root);
+    }
+
+    #[test]
+    fn items_do_not_collide() {
+        let storage = Storage::default();
+        let value = StorageValue::<u32>::new(&storage, "Pallet", "Value");
+        let map = StorageMap::<u32, u32>::new(&storage, "Pallet", "Map");
+        let other = StorageMap::<u32, u32>::new(&storage, "Other", "Map");
+
+        value.put(&1);
+        map.insert(&1, &10);
+        map.insert(&2, &20);
+        other.insert(&1, &100);
+
+        assert_eq!(value.get(), Some(1));
+        assert_eq!(map.get(&1), Some(10));
+        assert_eq!(other.get(&1), Some(100));
+        assert_eq!(map.iter().len(), 2);
+        assert!(map.iter().contains(&(2, 20)));
+
+        map.remove(&1);
+        value.kill();
+        assert!(!map.contains_key(&1));
+        assert_eq!(value.get(), None);
+        assert_eq!(other.iter(), vec![(1, 100)]);
+    }
+
+    #[test]
+    fn double_map_prefixes() {
+        let storage = Storage::default();
+        let map = StorageDoubleMap::<String, u32, bool>::new(&storage, "Pallet", "DoubleMap");
+
+        map.insert(&"alice".to_string(), &1, &true);
+        map.insert(&"alice".to_string(), &2, &false);
+        map.insert(&"bob".to_string(), &1, &true);
+
+        let mut alice = map.iter_prefix(&"alice".to_string());
+        alice.sort();
+        assert_eq!(alice, vec![(1, true), (2, false)]);
+        assert_eq!(map.iter().len(), 3);
+
+        map.remove_prefix(&"alice".to_string());
+        assert!(map.iter_prefix(&"alice".to_string()).is_empty());
+        assert_eq!(map.get(&"bob".to_string(), &1), Some(true));
+    }
+
+    #[test]
+    fn map_read_proof() {
+        let storage = Storage::default();
+        let map = StorageMap::<u32, u64>::new(&storage, "Pallet", "Map");
+        for i in 0..10 {
+            map.insert(&i, &(i as u64 * 10));
+        }
+        let root = storage.state_root();
+
+        let proof = map.read_proof(&3);
+        assert!(verify_read_proof(&root, &map.hashed_key(&3), Some(&30u64.encode()), &proof));
+        assert!(!verify_read_proof(&root, &map.hashed_key(&3), Some(&31u64.encode()), &proof));
+
+        let proof = map.read_proof(&42);
+        assert!(verify_read_proof(&root, &map.hashed_key(&42), None, &proof));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::support::crypto::{blake2_256, Hash};
+use crate::support::merkle::hash_node;
+use std::collections::BTreeMap;
+
+// Sparse Merkle trie over the key-value pairs of the state, which yields the state root
+// - every key is placed at the path `blake2_256(key)`, read bit by bit from the most significant one
+// - a leaf is hashed as `blake2_256(0x00 ++ path ++ blake2_256(value))`
+// - an inner node is hashed as `blake2_256(0x01 ++ left ++ right)`, like in `merkle`
+// - an empty subtree is the zero hash, and a subtree holding a single leaf is that leaf
+// The last rule keeps the trie as deep as needed to separate the paths, instead of 256 levels
+
+const EMPTY: Hash = [0; 32];
+
+// A leaf of the trie: the path of its key and the hash of its value
+type Leaf = (Hash, Hash);
+
+fn hash_leaf((path, value_hash): &Leaf) -> Hash {
+    let mut data = Vec::with_capacity(65);
+    data.push(0x00);
+    data.extend_from_slice(path);
+    data.extend_from_slice(value_hash);
+    blake2_256(&data)
+}
+
+// The bit of `path` at `depth`, which tells whether the path goes right (true) or left (false)
+fn bit(path: &Hash, depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+// The leaves of the trie, sorted by path
+fn leaves(pairs: &BTreeMap<Vec<u8>, Vec<u8>>) -> Vec<Leaf> {
+    let mut leaves = pairs
+        .iter()
+        .map(|(key, value)| (blake2_256(key), blake2_256(value)))
+        .collect::<Vec<_>>();
+    leaves.sort();
+    leaves
+}
+
+// Splits sorted leaves sharing the same first `depth` bits into the left and right subtrees
+fn split(leaves: &[Leaf], depth: usize) -> (&[Leaf], &[Leaf]) {
+    leaves.split_at(leaves.partition_point(|(path, _)| !bit(path, depth)))
+}
+
+fn subtree_root(leaves: &[Leaf], depth: usize) -> Hash {
+    match leaves {
+        [] => EMPTY,
+        [leaf] => hash_leaf(leaf),
+        _ => {
+            let (left, right) = split(leaves, depth);
+            hash_node(&subtree_root(left, depth + 1), &subtree_root(right, depth + 1))
+        }
+    }
+}
+
+pub fn state_root(pairs: &BTreeMap<Vec<u8>, Vec<u8>>) -> Hash {
+    subtree_root(&leaves(pairs), 0)
+}
+
+// Proof of the value of a key, or of its absence, in a state with a given root
+// Contains the roots of the sibling subtrees on the path of the key, from the top down, and the
+// leaf found at the end of the path, if any, which is the leaf of another key when it is absent
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct ReadProof {
+    pub siblings: Vec<Hash>,
+    pub leaf: Option<(Hash, Hash)>,
+}
+
+pub fn read_proof(pairs: &BTreeMap<Vec<u8>, Vec<u8>>, key: &[u8]) -> ReadProof {
+    let path = blake2_256(key);
+    let leaves = leaves(pairs);
+    let mut subtree = &leaves[..];
+    let mut siblings = Vec::new();
+    while subtree.len() > 1 {
+        let depth = siblings.len();
+        let (left, right) = split(subtree, depth);
+        if bit(&path, depth) {
+            siblings.push(subtree_root(left, depth + 1));
+            subtree = right;
+        } else {
+            siblings.push(subtree_root(right, depth + 1));
+            subtree = left;
+        }
+    }
+    ReadProof { siblings, leaf: subtree.first().copied() }
+}
+
+// Checks that `key` has `value` in the state with the given `root`, None meaning that it is absent
+pub fn verify_read_proof(root: &Hash, key: &[u8], value: Option<&[u8]>, proof: &ReadProof) -> bool {
+    let path = blake2_256(key);
+    if proof.siblings.len() > 256 {
+        return false;
+    }
+    let found = match (value, proof.leaf) {
+        (Some(value), Some(leaf)) if leaf == (path, blake2_256(value)) => hash_leaf(&leaf),
+        (Some(_), _) => return false,
+        (None, None) => EMPTY,
+        // the leaf of another key must be on the path of the key, and end the path
+        (None, Some(leaf)) => {
+            let on_path = (0..proof.siblings.len()).all(|depth| bit(&leaf.0, depth) == bit(&path, depth));
+            if leaf.0 == path || !on_path {
+                return false;
+            }
+            hash_leaf(&leaf)
+        }
+    };
+    let computed = proof.siblings.iter().enumerate().rev().fold(found, |hash, (depth, sibling)| {
+        if bit(&path, depth) {
+            hash_node(sibling, &hash)
+        } else {
+            hash_node(&hash, sibling)
+        }
+    });
+    computed == *root
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_proof, state_root, verify_read_proof};
+    use std::collections::BTreeMap;
+
+    fn pairs(count: u8) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        (0..count).map(|i| (vec![i], vec![i; 4])).collect()
+    }
+
+    #[test]
+    fn root_commits_to_every_pair() {
+        assert_eq!(state_root(&pairs(0)), [0; 32]);
+        assert_ne!(state_root(&pairs(1)), state_root(&pairs(2)));
+
+        let mut changed = pairs(10);
+        changed.insert(vec![3], vec![0]);
+        assert_ne!(state_root(&pairs(10)), state_root(&changed));
+
+        // the root doesn't depend on the order of insertion
+        let reversed = pairs(10).into_iter().rev().collect::<BTreeMap<_, _>>();
+        assert_eq!(state_root(&pairs(10)), state_root(&reversed));
+    }
+
+    #[test]
+    fn proofs_of_present_keys() {
+        for count in 1..=20 {
+            let pairs = pairs(count);
+            let root = state_root(&pairs);
+            for (key, value) in &pairs {
+                let proof = read_proof(&pairs, key);
+                assert!(verify_read_proof(&root, key, Some(value), &proof));
+                assert!(!verify_read_proof(&root, key, Some(&[1, 2, 3]), &proof));
+                assert!(!verify_read_proof(&root, key, None, &proof));
+            }
+        }
+    }
+
+    #[test]
+    fn proofs_of_absent_keys() {
+        for count in 0..=20 {
+            let pairs = pairs(count);
+            let root = state_root(&pairs);
+            let key = b"absent key";
+            let proof = read_proof(&pairs, key);
+            assert!(verify_read_proof(&root, key, None, &proof));
+            assert!(!verify_read_proof(&root, key, Some(&[0]), &proof));
+        }
+    }
+
+    #[test]
+    fn invalid_proofs_are_rejected() {
+        let pairs = pairs(8);
+        let root = state_root(&pairs);
+        let proof = read_proof(&pairs, &[2]);
+
+        // the proof of another key
+        assert!(!verify_read_proof(&root, &[3], Some(&[3; 4]), &proof));
+        // the proof of a key can't prove that another key is absent
+        assert!(!verify_read_proof(&root, &[3], None, &proof));
+        // a tampered sibling
+        let mut tampered = proof.clone();
+        tampered.siblings[0][0] ^= 1;
+        assert!(!verify_read_proof(&root, &[2], Some(&[2; 4]), &tampered));
+        // another root
+        assert!(!verify_read_proof(&state_root(&BTreeMap::new()), &[2], Some(&[2; 4]), &proof));
+    }
+}
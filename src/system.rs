@@ -1,83 +1,461 @@
+use crate::support::codec::{Decode, Encode, HasCompact};
+use crate::support::crypto::Hash;
+use crate::support::storage::{Storage, StorageMap, StorageValue};
+use crate::support::{DispatchError, Header, InvalidTransaction, Weight};
 use num::{One, Zero};
-use std::{collections::BTreeMap, ops::AddAssign};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, ops::AddAssign};
 
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + Copy + AddAssign;
-    type Nonce: Zero + One + Ord + Copy;
+    type AccountId: Ord + Clone + Debug + Encode + Decode;
+    type BlockNumber: Zero + One + Copy + Ord + AddAssign + Debug + Encode + Decode + HasCompact;
+    type Nonce: Zero + One + Ord + Copy + Debug + Encode + Decode;
+    // the aggregated event type of the runtime, generated by `#[macros::runtime]`
+    type RuntimeEvent: Debug + Encode + Decode;
+    // the origin of the calls of the runtime, which can be turned into a system origin
+    type RuntimeOrigin: Clone + Debug + From<Origin<Self::AccountId>> + Into<Origin<Self::AccountId>>;
+    // the total weight of the extrinsics of a block can't be more than this
+    const MAX_BLOCK_WEIGHT: Weight;
+}
+
+// Where a call comes from, which decides what the call is allowed to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin<AccountId> {
+    // a signed extrinsic, from the account which signed it
+    Signed(AccountId),
+    // the chain itself, with every privilege, e.g. a call dispatched by the sudo key
+    Root,
+    // an unsigned extrinsic, i.e. an inherent put in the block by its author
+    None,
+}
+
+// The account of a signed origin, any other origin is rejected
+pub fn ensure_signed<AccountId>(origin: impl Into<Origin<AccountId>>) -> Result<AccountId, DispatchError> {
+    match origin.into() {
+        Origin::Signed(who) => Ok(who),
+        _ => Err(DispatchError::BadOrigin),
+    }
+}
+
+pub fn ensure_root<AccountId>(origin: impl Into<Origin<AccountId>>) -> Result<(), DispatchError> {
+    match origin.into() {
+        Origin::Root => Ok(()),
+        _ => Err(DispatchError::BadOrigin),
+    }
+}
+
+pub fn ensure_none<AccountId>(origin: impl Into<Origin<AccountId>>) -> Result<(), DispatchError> {
+    match origin.into() {
+        Origin::None => Ok(()),
+        _ => Err(DispatchError::BadOrigin),
+    }
+}
+
+// An event deposited during block execution, together with the index of the extrinsic which emitted it
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct EventRecord<Event> {
+    pub extrinsic_index: u32,
+    pub event: Event,
+}
+
+// The events of the system pallet, which record the outcome of every extrinsic of a block
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event {
+    // the call of the extrinsic was dispatched successfully
+    ExtrinsicSuccess,
+    // the call of the extrinsic failed with `error`, its changes were reverted
+    ExtrinsicFailed { error: DispatchError },
+}
+
+// The initial state of the system pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "T::BlockNumber: Serialize", deserialize = "T::BlockNumber: Deserialize<'de>"))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // number of the genesis block, the first imported block is the next one
+    pub block_number: T::BlockNumber,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { block_number: T::BlockNumber::zero() }
+    }
 }
 
 // This is System Pallet
 // Handles the low level state transition functions of the blockchain
 // Contains block number (u32) and a map from the account to their nonce
-#[derive(Debug)]
+// Everything is kept in the storage, under the "System" prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pallet<T: Config> {
-    // block number
-    block_number: T::BlockNumber,
+    storage: Storage,
+    // block number of the last imported block
+    block_number: StorageValue<T::BlockNumber>,
     // map from account to their nonce (nonce is a number that is used only once, that counts the transactions of an account)
     // the key is the wallet and the value is the nonce (how many transactions have been made)
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    nonce: StorageMap<T::AccountId, T::Nonce>,
+    // events deposited during the execution of the current block, reset at the start of every block
+    events: StorageValue<Vec<EventRecord<T::RuntimeEvent>>>,
+    // index of the extrinsic being applied, recorded with the events it deposits
+    extrinsic_index: StorageValue<u32>,
+    // weight used by the extrinsics of the current block, reset at the start of every block
+    block_weight: StorageValue<Weight>,
+    // map from block number to the hash of its header, starting with the genesis block
+    // the hash of the genesis block identifies the chain, it is included in the payload signed by the callers of extrinsics
+    // the hash of a block is only stored once its child is imported, see `block_hash`
+    block_hash: StorageMap<T::BlockNumber, Hash>,
+    // number of the genesis block, whose hash identifies the chain
+    genesis_number: StorageValue<T::BlockNumber>,
+    // parent hash, extrinsics root and timestamp of the last imported block
+    // with the block number and the state root, they make up the header of the last imported block
+    parent_hash: StorageValue<Hash>,
+    extrinsics_root: StorageValue<Hash>,
+    // timestamp of the last imported block, in milliseconds since the unix epoch
+    timestamp: StorageValue<u64>,
 }
 
 impl<T: Config> Pallet<T>
 {
-    pub fn new() -> Self {
+    pub fn new(storage: &Storage) -> Self {
         Self {
-            block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::new(),
+            storage: storage.clone(),
+            block_number: StorageValue::new(storage, "System", "Number"),
+            nonce: StorageMap::new(storage, "System", "Nonce"),
+            events: StorageValue::new(storage, "System", "Events"),
+            extrinsic_index: StorageValue::new(storage, "System", "ExtrinsicIndex"),
+            block_weight: StorageValue::new(storage, "System", "BlockWeight"),
+            block_hash: StorageMap::new(storage, "System", "BlockHash"),
+            genesis_number: StorageValue::new(storage, "System", "GenesisNumber"),
+            parent_hash: StorageValue::new(storage, "System", "ParentHash"),
+            extrinsics_root: StorageValue::new(storage, "System", "ExtrinsicsRoot"),
+            timestamp: StorageValue::new(storage, "System", "Timestamp"),
         }
     }
 
+    // Sets up the genesis state, before the first block
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        self.block_number.put(&config.block_number);
+        self.genesis_number.put(&config.block_number);
+    }
+
+    // The storage shared by all the pallets of the runtime
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
     pub fn block_number(&self) -> T::BlockNumber {
-        self.block_number
+        self.block_number.get().unwrap_or_else(T::BlockNumber::zero)
     }
 
+    // The header of the last imported block
+    // Its state root is the current state root, since the state has not changed since then
+    pub fn last_header(&self) -> Header<T::BlockNumber> {
+        Header {
+            parent_hash: self.parent_hash.get().unwrap_or_default(),
+            block_number: self.block_number(),
+            state_root: self.storage.state_root(),
+            extrinsics_root: self.extrinsics_root.get().unwrap_or_default(),
+            timestamp: self.timestamp(),
+        }
+    }
+
+    // The hash of the last imported block is not stored yet, it is computed from its header
+    pub fn block_hash(&self, block_number: T::BlockNumber) -> Option<Hash> {
+        if block_number == self.block_number() {
+            return Some(self.last_header().hash());
+        }
+        self.block_hash.get(&block_number)
+    }
+
+    pub fn set_block_hash(&mut self, block_number: T::BlockNumber, hash: Hash) {
+        self.block_hash.insert(&block_number, &hash);
+    }
+
+    pub fn genesis_hash(&self) -> Hash {
+        self.block_hash(self.genesis_number.get().unwrap_or_else(T::BlockNumber::zero)).unwrap_or_default()
+    }
+
+    // The hash of the last imported block, which is the parent of the next block
+    pub fn parent_hash(&self) -> Hash {
+        self.last_header().hash()
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp.get().unwrap_or_default()
+    }
+
+    // Starts a new block on top of the last imported block, which is its parent
+    // The hash of the parent is stored, and the header of the new block becomes the last header
+    // The state root of the new block is only known once its extrinsics are applied
+    pub fn initialize_block(&mut self, header: &Header<T::BlockNumber>) {
+        self.set_block_hash(self.block_number(), header.parent_hash);
+        self.reset_events();
+        self.block_weight.kill();
+        self.inc_block_number();
+        self.parent_hash.put(&header.parent_hash);
+        self.extrinsics_root.put(&header.extrinsics_root);
+        self.timestamp.put(&header.timestamp);
+    }
 
     pub fn inc_block_number(&mut self) {
         // fails on overflow as expected behavior, because block number should never overflow
-        self.block_number += T::BlockNumber::one()
+        let mut block_number = self.block_number();
+        block_number += T::BlockNumber::one();
+        self.block_number.put(&block_number);
     }
 
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
-        let nonce = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
-        self.nonce.insert(who.clone(), nonce + T::Nonce::one());
+        let nonce = self.get_nonce(who);
+        self.nonce.insert(who, &(nonce + T::Nonce::one()));
     }
 
     pub fn get_nonce(&self, who: &T::AccountId) -> T::Nonce {
-        *self.nonce.get(who).unwrap_or(&T::Nonce::zero())
+        self.nonce.get(who).unwrap_or_else(T::Nonce::zero)
+    }
+
+    // Removes what is kept for an account which no longer exists, e.g. once the balances pallet
+    // reaped it: its nonce starts again from zero
+    pub fn kill_account(&mut self, who: &T::AccountId) {
+        self.nonce.remove(who);
+    }
+
+    // Checks that `nonce` is the next nonce of the account
+    // A lower nonce was already used (stale), a higher one would leave a gap (future)
+    pub fn check_nonce(&self, who: &T::AccountId, nonce: T::Nonce) -> Result<(), InvalidTransaction> {
+        let expected = self.get_nonce(who);
+        if nonce < expected {
+            return Err(InvalidTransaction::Stale);
+        }
+        if nonce > expected {
+            return Err(InvalidTransaction::Future);
+        }
+        Ok(())
+    }
+
+    pub fn block_weight(&self) -> Weight {
+        self.block_weight.get().unwrap_or_default()
+    }
+
+    // Adds the weight of an extrinsic to the weight of the block
+    // The extrinsic doesn't fit in the block if the block would go over `MAX_BLOCK_WEIGHT`
+    pub fn register_weight(&mut self, weight: Weight) -> Result<(), InvalidTransaction> {
+        let block_weight = self.block_weight().checked_add(weight).filter(|weight| *weight <= T::MAX_BLOCK_WEIGHT);
+        let block_weight = block_weight.ok_or(InvalidTransaction::ExhaustsResources)?;
+        self.block_weight.put(&block_weight);
+        Ok(())
+    }
+
+    // Adds the weight of what the block does outside of its extrinsics, e.g. in the hooks of the
+    // pallets. It is registered even over `MAX_BLOCK_WEIGHT`, it then leaves no room for extrinsics
+    pub fn register_extra_weight(&mut self, weight: Weight) {
+        self.block_weight.put(&self.block_weight().saturating_add(weight));
+    }
+
+    // Gives back weight registered by an extrinsic which it didn't use
+    pub fn refund_weight(&mut self, weight: Weight) {
+        self.block_weight.put(&self.block_weight().saturating_sub(weight));
+    }
+
+    // Sets the index of the extrinsic being applied, until the next one
+    pub fn set_extrinsic_index(&mut self, extrinsic_index: u32) {
+        self.extrinsic_index.put(&extrinsic_index);
+    }
+
+    // Deposits an event of the extrinsic being applied
+    // Pallets deposit their events here directly, so the events of a failed extrinsic are
+    // reverted together with the rest of its changes
+    pub fn deposit_event(&mut self, event: T::RuntimeEvent) {
+        let extrinsic_index = self.extrinsic_index.get().unwrap_or_default();
+        let mut events = self.events();
+        events.push(EventRecord { extrinsic_index, event });
+        self.events.put(&events);
+    }
+
+    // Deposits the outcome of the call of the extrinsic being applied, once it is dispatched
+    // This is done outside of the storage transaction layer of the call, so a failed call is
+    // recorded too
+    pub fn note_applied_extrinsic<R>(&mut self, result: &Result<R, DispatchError>)
+    where
+        T::RuntimeEvent: From<Event>,
+    {
+        let event = match result {
+            Ok(_) => Event::ExtrinsicSuccess,
+            Err(error) => Event::ExtrinsicFailed { error: *error },
+        };
+        self.deposit_event(event.into());
+    }
+
+    pub fn events(&self) -> Vec<EventRecord<T::RuntimeEvent>> {
+        self.events.get().unwrap_or_default()
+    }
+
+    pub fn reset_events(&mut self) {
+        self.events.kill();
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::support::{storage::Storage, Header, InvalidTransaction};
+
+    #[derive(Debug)]
     struct TestConfig;
 
     impl super::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = String;
+        type RuntimeOrigin = super::Origin<String>;
+        const MAX_BLOCK_WEIGHT: u64 = 100;
+    }
+
+    fn new_pallet() -> super::Pallet<TestConfig> {
+        super::Pallet::new(&Storage::default())
     }
 
     #[test]
     fn init_system() {
-        let _pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let _pallet = new_pallet();
         assert_eq!(_pallet.block_number(), 0);
     }
 
     #[test]
     fn inc_block_number() {
-        let mut pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut pallet = new_pallet();
         pallet.inc_block_number();
         assert_eq!(pallet.block_number(), 1);
     }
 
     #[test]
     fn inc_nonce() {
-        let mut pallet: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut pallet = new_pallet();
         let alice = "Alice".to_string();
         pallet.inc_nonce(&alice);
         assert_eq!(pallet.get_nonce(&alice), 1);
         pallet.inc_nonce(&alice);
         assert_eq!(pallet.get_nonce(&alice), 2);
+        pallet.kill_account(&alice);
+        assert_eq!(pallet.get_nonce(&alice), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn block_hashes() {
+        let mut pallet = new_pallet();
+        let genesis_hash = pallet.genesis_hash();
+        assert_eq!(genesis_hash, pallet.last_header().hash());
+        assert_eq!(pallet.parent_hash(), genesis_hash);
+
+        let header = Header {
+            parent_hash: genesis_hash,
+            block_number: 1,
+            state_root: [0; 32],
+            extrinsics_root: [2; 32],
+            timestamp: 6_000,
+        };
+        pallet.initialize_block(&header);
+        assert_eq!(pallet.genesis_hash(), genesis_hash);
+        assert_eq!(pallet.block_hash(0), Some(genesis_hash));
+        assert_eq!(pallet.last_header(), Header { state_root: pallet.storage().state_root(), ..header });
+        assert_eq!(pallet.block_hash(1), Some(pallet.parent_hash()));
+        assert_eq!(pallet.block_hash(2), None);
+    }
+
+    #[test]
+    fn genesis_block_number() {
+        let mut pallet = new_pallet();
+        pallet.build_genesis(&super::GenesisConfig { block_number: 10 });
+        assert_eq!(pallet.block_number(), 10);
+        let genesis_hash = pallet.genesis_hash();
+        assert_eq!(genesis_hash, pallet.last_header().hash());
+
+        let header = Header {
+            parent_hash: genesis_hash,
+            block_number: 11,
+            state_root: [0; 32],
+            extrinsics_root: [0; 32],
+            timestamp: 6_000,
+        };
+        pallet.initialize_block(&header);
+        assert_eq!(pallet.block_number(), 11);
+        assert_eq!(pallet.genesis_hash(), genesis_hash);
+        assert_eq!(pallet.block_hash(10), Some(genesis_hash));
+    }
+
+    #[test]
+    fn block_weight_is_bounded() {
+        let mut pallet = new_pallet();
+        assert_eq!(pallet.register_weight(60), Ok(()));
+        assert_eq!(pallet.register_weight(50), Err(InvalidTransaction::ExhaustsResources));
+        assert_eq!(pallet.register_weight(u64::MAX), Err(InvalidTransaction::ExhaustsResources));
+        pallet.refund_weight(20);
+        assert_eq!(pallet.register_weight(50), Ok(()));
+        assert_eq!(pallet.block_weight(), 90);
+        // the weight used outside of extrinsics leaves less room for them
+        pallet.register_extra_weight(10);
+        assert_eq!(pallet.register_weight(1), Err(InvalidTransaction::ExhaustsResources));
+
+        let header = Header {
+            parent_hash: pallet.parent_hash(),
+            block_number: 1,
+            state_root: [0; 32],
+            extrinsics_root: [0; 32],
+            timestamp: 6_000,
+        };
+        pallet.initialize_block(&header);
+        assert_eq!(pallet.block_weight(), 0);
+    }
+
+    #[test]
+    fn ensure_origin() {
+        use super::{ensure_none, ensure_root, ensure_signed, Origin};
+        use crate::support::DispatchError;
+
+        let alice = Origin::Signed("Alice".to_string());
+        assert_eq!(ensure_signed(alice.clone()), Ok("Alice".to_string()));
+        assert_eq!(ensure_root(alice.clone()), Err(DispatchError::BadOrigin));
+        assert_eq!(ensure_none(alice), Err(DispatchError::BadOrigin));
+        assert_eq!(ensure_signed(Origin::<String>::Root), Err(DispatchError::BadOrigin));
+        assert_eq!(ensure_root(Origin::<String>::Root), Ok(()));
+        assert_eq!(ensure_signed(Origin::<String>::None), Err(DispatchError::BadOrigin));
+        assert_eq!(ensure_none(Origin::<String>::None), Ok(()));
+    }
+
+    #[test]
+    fn check_nonce() {
+        let mut pallet = new_pallet();
+        let alice = "Alice".to_string();
+        assert_eq!(pallet.check_nonce(&alice, 0), Ok(()));
+        assert_eq!(pallet.check_nonce(&alice, 1), Err(InvalidTransaction::Future));
+        pallet.inc_nonce(&alice);
+        assert_eq!(pallet.check_nonce(&alice, 0), Err(InvalidTransaction::Stale));
+        assert_eq!(pallet.check_nonce(&alice, 1), Ok(()));
+    }
+
+    #[test]
+    fn deposit_and_reset_events() {
+        let mut pallet = new_pallet();
+        pallet.deposit_event("first".to_string());
+        pallet.set_extrinsic_index(2);
+        pallet.deposit_event("second".to_string());
+        assert_eq!(pallet.events(), vec![
+            super::EventRecord { extrinsic_index: 0, event: "first".to_string() },
+            super::EventRecord { extrinsic_index: 2, event: "second".to_string() },
+        ]);
+        pallet.reset_events();
+        assert!(pallet.events().is_empty());
+    }
+
+    #[test]
+    fn state_is_kept_in_storage() {
+        let storage = Storage::default();
+        let mut pallet = super::Pallet::<TestConfig>::new(&storage);
+        let root = storage.state_root();
+        pallet.inc_nonce(&"Alice".to_string());
+        assert_ne!(storage.state_root(), root);
+
+        // another view of the same storage sees the same state
+        let other = super::Pallet::<TestConfig>::new(&storage);
+        assert_eq!(other.get_nonce(&"Alice".to_string()), 1);
+    }
+}
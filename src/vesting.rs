@@ -0,0 +1,439 @@
+use crate::support::currency::{Currency, ExistenceRequirement, LockIdentifier, LockableCurrency};
+use crate::support::storage::{Storage, StorageMap};
+use crate::support::{DispatchResult, FromStorage, Hooks};
+use crate::system;
+use num::{CheckedDiv, CheckedMul, One, Saturating, Zero};
+use serde::{Deserialize, Serialize};
+
+// The funds of a vesting account which are not vested yet are locked under this id
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self>>> + Sized {
+    // the currency whose funds are vested, a number of blocks is turned into a balance to compute
+    // how much is vested after that many blocks
+    type Currency: LockableCurrency<
+        Self::AccountId,
+        Balance: From<Self::BlockNumber> + CheckedMul + CheckedDiv + One + Eq,
+    > + FromStorage;
+    // the smallest amount which can be transferred with a vesting schedule
+    const MIN_VESTED_TRANSFER: BalanceOf<Self>;
+    // the maximum number of schedules of an account
+    const MAX_VESTING_SCHEDULES: usize;
+}
+
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+pub type ScheduleOf<T> = VestingInfo<BalanceOf<T>, <T as system::Config>::BlockNumber>;
+
+// This is Vesting Pallet
+// Funds given with a vesting schedule are released linearly, block after block. Until then they
+// stay in the account but are locked, so they can't be transferred
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pallet<T: Config> {
+    storage: Storage,
+    // the schedules of every vesting account, an account without schedules isn't kept
+    vesting: StorageMap<T::AccountId, Vec<ScheduleOf<T>>>,
+}
+
+// `locked` is released by `per_block` every block from `starting_block` on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, macros::Encode, macros::Decode)]
+pub struct VestingInfo<Balance, BlockNumber> {
+    pub locked: Balance,
+    pub per_block: Balance,
+    pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+    Balance: From<BlockNumber> + CheckedMul + CheckedDiv + Saturating + Zero + One + PartialOrd + Copy,
+    BlockNumber: Copy,
+{
+    // What is still locked at block `now`
+    pub fn locked_at(&self, now: BlockNumber) -> Balance {
+        let elapsed = Balance::from(now).saturating_sub(Balance::from(self.starting_block));
+        match self.per_block.checked_mul(&elapsed) {
+            Some(vested) => self.locked.saturating_sub(vested),
+            None => Balance::zero(),
+        }
+    }
+
+    // The block at which everything is vested, as a balance
+    fn ending_block(&self) -> Balance {
+        let blocks = self.locked.checked_div(&self.per_block).unwrap_or_else(Balance::zero);
+        // the last block releases what is left when `locked` isn't a multiple of `per_block`
+        let blocks = match blocks.checked_mul(&self.per_block) {
+            Some(released) if released < self.locked => blocks.saturating_add(Balance::one()),
+            _ => blocks,
+        };
+        Balance::from(self.starting_block).saturating_add(blocks)
+    }
+}
+
+// The initial state of the vesting pallet, part of the genesis config of the runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::AccountId: Serialize, BalanceOf<T>: Serialize, T::BlockNumber: Serialize",
+    deserialize = "T::AccountId: Deserialize<'de>, BalanceOf<T>: Deserialize<'de>, T::BlockNumber: Deserialize<'de>"
+))]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+    // the schedules of the accounts vesting from the genesis on, each account must already hold
+    // what its schedule locks
+    pub vesting: Vec<(T::AccountId, ScheduleOf<T>)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { vesting: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum Event<T: Config> {
+    // `account` got a new vesting schedule, at `schedule_index` of its schedules
+    Created { account: T::AccountId, schedule_index: u32 },
+    // the lock of `account` was updated, `unvested` is still locked
+    Updated { account: T::AccountId, unvested: BalanceOf<T> },
+    // everything given to `account` with a schedule is vested, and its lock is removed
+    Completed { account: T::AccountId },
+}
+
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the account has no vesting schedule
+    NotVesting,
+    // the account already has `Config::MAX_VESTING_SCHEDULES` schedules
+    AtMaxVestingSchedules,
+    // the amount transferred is below `Config::MIN_VESTED_TRANSFER`
+    AmountLow,
+    // there is no schedule at this index
+    ScheduleIndexOutOfBounds,
+    // the schedule releases nothing per block
+    InvalidScheduleParams,
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    // Unlocks what is vested of the funds of the caller
+    #[weight(10)]
+    pub fn vest(&mut self, origin: T::RuntimeOrigin) -> DispatchResult {
+        let who = system::ensure_signed(origin)?;
+        let schedules = self.vesting.get(&who).ok_or(Error::NotVesting)?;
+        self.update_vesting(&who, schedules);
+        Ok(())
+    }
+
+    // Transfers `schedule.locked` to `target`, where it is released following `schedule`
+    #[weight(15)]
+    pub fn vested_transfer(&mut self, origin: T::RuntimeOrigin, target: T::AccountId,
+                           schedule: ScheduleOf<T>) -> DispatchResult {
+        let source = system::ensure_signed(origin)?;
+        if schedule.locked < T::MIN_VESTED_TRANSFER {
+            return Err(Error::AmountLow.into());
+        }
+        // a schedule which is already over would be dropped right after it is created
+        let now = system::Pallet::<T>::new(&self.storage).block_number();
+        if schedule.per_block.is_zero() || schedule.locked_at(now).is_zero() {
+            return Err(Error::InvalidScheduleParams.into());
+        }
+        let mut schedules = self.vesting.get(&target).unwrap_or_default();
+        if schedules.len() >= T::MAX_VESTING_SCHEDULES {
+            return Err(Error::AtMaxVestingSchedules.into());
+        }
+
+        T::Currency::from_storage(&self.storage)
+            .transfer(&source, &target, schedule.locked, ExistenceRequirement::AllowDeath)?;
+        schedules.push(schedule);
+        let schedule_index = schedules.len() as u32 - 1;
+        self.deposit_event(Event::Created { account: target.clone(), schedule_index });
+        self.update_vesting(&target, schedules);
+        Ok(())
+    }
+
+    // Merges two schedules of the caller into one, releasing what is left of both from the
+    // current block until the later of their ends, so the caller can take more schedules
+    #[weight(10)]
+    pub fn merge_schedules(&mut self, origin: T::RuntimeOrigin, schedule1_index: u32,
+                           schedule2_index: u32) -> DispatchResult {
+        let who = system::ensure_signed(origin)?;
+        let mut schedules = self.vesting.get(&who).ok_or(Error::NotVesting)?;
+        let (first, second) = (schedule1_index as usize, schedule2_index as usize);
+        if first >= schedules.len() || second >= schedules.len() {
+            return Err(Error::ScheduleIndexOutOfBounds.into());
+        }
+        if first == second {
+            return Ok(());
+        }
+
+        // the later one is removed first, so the index of the other one doesn't move
+        let later = schedules.remove(first.max(second));
+        let earlier = schedules.remove(first.min(second));
+        let now = system::Pallet::<T>::new(&self.storage).block_number();
+        schedules.extend(Self::merge(earlier, later, now));
+        self.update_vesting(&who, schedules);
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            storage: storage.clone(),
+            vesting: StorageMap::new(storage, "Vesting", "Vesting"),
+        }
+    }
+
+    // Sets up the genesis state, before the first block
+    // Panics if an account doesn't hold what it vests, or has too many schedules
+    pub fn build_genesis(&mut self, config: &GenesisConfig<T>) {
+        let currency = T::Currency::from_storage(&self.storage);
+        for (who, schedule) in &config.vesting {
+            assert!(!schedule.per_block.is_zero(), "the schedule of {:?} releases nothing", who);
+            let mut schedules = self.vesting.get(who).unwrap_or_default();
+            assert!(schedules.len() < T::MAX_VESTING_SCHEDULES, "{:?} has too many schedules", who);
+            // all the schedules of an account vest from the same funds
+            let locked = schedules.iter().fold(schedule.locked, |total, schedule| {
+                total.saturating_add(schedule.locked)
+            });
+            assert!(currency.free_balance(who) >= locked, "{:?} can't vest more than it holds", who);
+            schedules.push(*schedule);
+            self.set_vesting(who, schedules);
+        }
+    }
+
+    // Events are deposited into the event log of the system pallet
+    fn deposit_event(&mut self, event: Event<T>) {
+        system::Pallet::<T>::new(&self.storage).deposit_event(event.into());
+    }
+
+    // Stores the schedules of `who` without the ones which are over, and locks what is still locked
+    // Returns what is locked
+    fn set_vesting(&mut self, who: &T::AccountId,
+                   schedules: Vec<ScheduleOf<T>>) -> BalanceOf<T> {
+        let now = system::Pallet::<T>::new(&self.storage).block_number();
+        let schedules: Vec<_> = schedules.into_iter().filter(|schedule| !schedule.locked_at(now).is_zero()).collect();
+        let locked = schedules.iter().fold(BalanceOf::<T>::zero(), |total, schedule| {
+            total.saturating_add(schedule.locked_at(now))
+        });
+        let mut currency = T::Currency::from_storage(&self.storage);
+        if schedules.is_empty() {
+            self.vesting.remove(who);
+            currency.remove_lock(VESTING_ID, who);
+        } else {
+            self.vesting.insert(who, &schedules);
+            currency.set_lock(VESTING_ID, who, locked);
+        }
+        locked
+    }
+
+    // Same as `set_vesting`, with an event telling what is still locked
+    fn update_vesting(&mut self, who: &T::AccountId, schedules: Vec<ScheduleOf<T>>) {
+        let unvested = self.set_vesting(who, schedules);
+        if unvested.is_zero() {
+            self.deposit_event(Event::Completed { account: who.clone() });
+        } else {
+            self.deposit_event(Event::Updated { account: who.clone(), unvested });
+        }
+    }
+
+    // The schedule releasing what is left of both schedules at `now`, from `now` or the later of
+    // their starts, until the later of their ends. `None` if both are over
+    fn merge(first: ScheduleOf<T>, second: ScheduleOf<T>,
+             now: T::BlockNumber) -> Option<ScheduleOf<T>> {
+        let (first_locked, second_locked) = (first.locked_at(now), second.locked_at(now));
+        match (first_locked.is_zero(), second_locked.is_zero()) {
+            (true, true) => return None,
+            (true, false) => return Some(second),
+            (false, true) => return Some(first),
+            (false, false) => {}
+        }
+
+        let locked = first_locked.saturating_add(second_locked);
+        let starting_block = now.max(first.starting_block).max(second.starting_block);
+        let ending_block = max(first.ending_block(), second.ending_block());
+        let duration = ending_block.saturating_sub(BalanceOf::<T>::from(starting_block));
+        let per_block = match locked.checked_div(&duration) {
+            Some(per_block) => max(per_block, BalanceOf::<T>::one()),
+            None => locked,
+        };
+        Some(VestingInfo { locked, per_block, starting_block })
+    }
+}
+
+// The vesting state of the accounts, as read by the users of the chain
+#[allow(dead_code)]
+impl<T: Config> Pallet<T> {
+    pub fn vesting(&self, who: &T::AccountId) -> Vec<ScheduleOf<T>> {
+        self.vesting.get(who).unwrap_or_default()
+    }
+
+    // What is still locked of the funds of `who` at the current block, or `None` if `who` isn't
+    // vesting
+    pub fn vesting_balance(&self, who: &T::AccountId) -> Option<BalanceOf<T>> {
+        let now = system::Pallet::<T>::new(&self.storage).block_number();
+        let schedules = self.vesting.get(who)?;
+        Some(schedules.iter().fold(BalanceOf::<T>::zero(), |total, schedule| {
+            total.saturating_add(schedule.locked_at(now))
+        }))
+    }
+}
+
+fn max<B: PartialOrd>(a: B, b: B) -> B {
+    if a > b { a } else { b }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, Event, GenesisConfig, VestingInfo};
+    use crate::balances;
+    use crate::mock::{self, TestConfig, TestEvent};
+    use crate::support::storage::Storage;
+    use crate::support::DispatchError;
+    use crate::system::{self, Origin};
+
+    impl super::Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        const MIN_VESTED_TRANSFER: u128 = 20;
+        const MAX_VESTING_SCHEDULES: usize = 2;
+    }
+
+    fn signed(who: &str) -> Origin<String> {
+        Origin::Signed(who.to_string())
+    }
+
+    fn schedule(locked: u128, per_block: u128, starting_block: u32) -> VestingInfo<u128, u32> {
+        VestingInfo { locked, per_block, starting_block }
+    }
+
+    fn set_block_number(storage: &Storage, number: u32) {
+        let mut system = system::Pallet::<TestConfig>::new(storage);
+        while system.block_number() < number {
+            system.inc_block_number();
+        }
+    }
+
+    fn vesting_events(storage: &Storage) -> Vec<Event<TestConfig>> {
+        mock::events(storage)
+            .into_iter()
+            .filter_map(|event| match event {
+                TestEvent::Vesting(event) => Some(event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Alice holds 1000 and Bob 100
+    fn new_pallets(storage: &Storage) -> (super::Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+        let mut balances = balances::Pallet::new(storage);
+        balances.build_genesis(&balances::GenesisConfig {
+            balances: vec![("Alice".to_string(), 1000), ("Bob".to_string(), 100)],
+        });
+        (super::Pallet::new(storage), balances)
+    }
+
+    #[test]
+    fn schedules_release_linearly() {
+        let schedule = schedule(100, 30, 5);
+        assert_eq!(schedule.locked_at(0), 100);
+        assert_eq!(schedule.locked_at(5), 100);
+        assert_eq!(schedule.locked_at(6), 70);
+        assert_eq!(schedule.locked_at(8), 10);
+        assert_eq!(schedule.locked_at(9), 0);
+        assert_eq!(schedule.ending_block(), 9);
+        assert_eq!(super::VestingInfo::<u128, u32> { per_block: u128::MAX, ..schedule }.locked_at(7), 0);
+    }
+
+    #[test]
+    fn vested_transfer_locks_the_funds() {
+        let storage = Storage::default();
+        let (mut vesting, mut balances) = new_pallets(&storage);
+        set_block_number(&storage, 1);
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(10, 1, 1)), Err(Error::AmountLow.into()));
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(100, 0, 1)), Err(Error::InvalidScheduleParams.into()));
+        // the schedule is over at block 11
+        set_block_number(&storage, 12);
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(100, 10, 1)), Err(Error::InvalidScheduleParams.into()));
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(100, 10, 12)), Ok(()));
+        assert_eq!(balances.balance(&"Bob".to_string()), 200);
+        assert_eq!(balances.account(&"Bob".to_string()).frozen, 100);
+        assert_eq!(vesting.vesting_balance(&"Bob".to_string()), Some(100));
+
+        // only what isn't vested can be transferred
+        assert_eq!(balances.transfer(signed("Bob"), "Alice".to_string(), 101), Err(balances::Error::LiquidityRestrictions.into()));
+        assert!(balances.transfer(signed("Bob"), "Alice".to_string(), 100).is_ok());
+        assert_eq!(vesting_events(&storage), vec![
+            Event::Created { account: "Bob".to_string(), schedule_index: 0 },
+            Event::Updated { account: "Bob".to_string(), unvested: 100 },
+        ]);
+        balances.assert_invariants();
+    }
+
+    #[test]
+    fn vest_unlocks_what_is_vested() {
+        let storage = Storage::default();
+        let (mut vesting, balances) = new_pallets(&storage);
+        assert_eq!(vesting.vest(signed("Bob")), Err(Error::NotVesting.into()));
+        assert_eq!(vesting.vest(Origin::Root), Err(DispatchError::BadOrigin));
+        vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(100, 30, 2)).unwrap();
+
+        // the lock only follows the schedule when the account vests
+        set_block_number(&storage, 4);
+        assert_eq!(vesting.vesting_balance(&"Bob".to_string()), Some(40));
+        assert_eq!(balances.account(&"Bob".to_string()).frozen, 100);
+        assert_eq!(vesting.vest(signed("Bob")), Ok(()));
+        assert_eq!(balances.account(&"Bob".to_string()).frozen, 40);
+
+        set_block_number(&storage, 6);
+        assert_eq!(vesting.vest(signed("Bob")), Ok(()));
+        assert_eq!(balances.account(&"Bob".to_string()).frozen, 0);
+        assert_eq!(balances.locks(&"Bob".to_string()), vec![]);
+        assert_eq!(vesting.vesting_balance(&"Bob".to_string()), None);
+        assert_eq!(vesting_events(&storage).last(), Some(&Event::Completed { account: "Bob".to_string() }));
+        assert_eq!(vesting.vest(signed("Bob")), Err(Error::NotVesting.into()));
+    }
+
+    #[test]
+    fn schedules_are_limited_and_merged() {
+        let storage = Storage::default();
+        let (mut vesting, balances) = new_pallets(&storage);
+        vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(100, 10, 0)).unwrap();
+        vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(60, 20, 4)).unwrap();
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(20, 1, 0)), Err(Error::AtMaxVestingSchedules.into()));
+
+        set_block_number(&storage, 2);
+        assert_eq!(vesting.merge_schedules(signed("Bob"), 0, 2), Err(Error::ScheduleIndexOutOfBounds.into()));
+        assert_eq!(vesting.merge_schedules(signed("Charlie"), 0, 1), Err(Error::NotVesting.into()));
+        assert_eq!(vesting.merge_schedules(signed("Bob"), 1, 0), Ok(()));
+        // 80 + 60 are left, released from block 4, when the second one starts, to block 10, when
+        // the first one ends
+        assert_eq!(vesting.vesting(&"Bob".to_string()), vec![schedule(140, 23, 4)]);
+        assert_eq!(balances.account(&"Bob".to_string()).frozen, 140);
+
+        // there is room for another schedule
+        assert_eq!(vesting.vested_transfer(signed("Alice"), "Bob".to_string(), schedule(20, 1, 0)), Ok(()));
+        assert_eq!(vesting.merge_schedules(signed("Bob"), 1, 1), Ok(()));
+        assert_eq!(vesting.vesting(&"Bob".to_string()).len(), 2);
+    }
+
+    #[test]
+    fn genesis() {
+        let storage = Storage::default();
+        let (mut vesting, balances) = new_pallets(&storage);
+        vesting.build_genesis(&GenesisConfig { vesting: vec![("Alice".to_string(), schedule(500, 50, 0))] });
+        assert_eq!(vesting.vesting(&"Alice".to_string()), vec![schedule(500, 50, 0)]);
+        assert_eq!(balances.account(&"Alice".to_string()).frozen, 500);
+        assert_eq!(vesting_events(&storage), vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "\"Alice\" can't vest more than it holds")]
+    fn genesis_schedules_vest_from_the_same_funds() {
+        let storage = Storage::default();
+        let (mut vesting, _) = new_pallets(&storage);
+        vesting.build_genesis(&GenesisConfig {
+            vesting: vec![("Alice".to_string(), schedule(600, 50, 0)), ("Alice".to_string(), schedule(600, 50, 0))],
+        });
+    }
+}